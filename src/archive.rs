@@ -0,0 +1,678 @@
+/* SQLite blog archive
+ *
+ * Copyright (C) 2016 TyanNN <TyanNN@cocaine.ninja>
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along
+ * with this program; if not, write to the Free Software Foundation, Inc.,
+ * 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+*/
+
+//! Архив блогов в локальной базе SQLite, включается фичей `archive`.
+//!
+//! [`Archive::archive_blog`](struct.Archive.html#method.archive_blog) проходит
+//! блог по страницам, для каждого поста получает полный текст и комменты и
+//! сохраняет их вместе с профилями авторов. Пост с комментами записывается
+//! одной транзакцией, а после каждой страницы запоминается следующая, так
+//! что прерванный обход можно просто запустить заново: он продолжится с
+//! той же страницы, а уже сохранённые посты будут пропущены. Новые посты,
+//! появившиеся за это время, сдвигают старые на следующие страницы, поэтому
+//! ничего не теряется.
+//!
+//! Когда блог пройден до конца, следующие запуски только догоняют его:
+//! обход идёт с первой страницы и останавливается на первой странице, где
+//! нет ни одного нового поста. Профили, которые не удалось загрузить, не
+//! прерывают обход, а попадают в
+//! [`ArchiveStats::failed_users`](struct.ArchiveStats.html#structfield.failed_users)
+//! и будут запрошены снова в следующий раз.
+//!
+//! ```no_run
+//! # let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+//! let mut archive = libtabun::archive::Archive::open("lighthouse.sqlite").unwrap();
+//! let stats = archive.archive_blog(&mut user, "lighthouse").unwrap();
+//! println!("Сохранено постов: {}, комментов: {}", stats.posts, stats.comments);
+//! ```
+//!
+//! # Схема
+//!
+//! Логические значения хранятся как 0/1, даты - как на сайте.
+//!
+//! - `posts` - посты: `id`, `blog`, `title`, `body` (полный текст), `date`,
+//!   `author`, `comments_count`, `draft`, `topic_type` (`topic`, `question`,
+//!   `link`, `photoset`), `link_url` и `link_clicks` (только у постов-ссылок),
+//!   `deleted` (пост пропал из блога: удалён, перенесён или скрыт в черновики)
+//! - `post_tags` - тэги постов: `post_id`, `position`, `tag`
+//! - `photos` - фотографии фотосетов: `post_id`, `position`, `url`, `description`
+//! - `comments` - комменты: `id`, `post_id`, `parent` (0 - ответ на пост),
+//!   `author`, `date`, `votes`, `body`, `deleted`
+//! - `users` - профили: `username`, `id`, `realname`, `skill`, `rating`,
+//!   `userpic`, `description`, `publications`, `favourites`, `friends`
+//! - `user_info` - поля вроде даты рождения из профиля: `username`, `key`, `value`
+//! - `user_blogs` - блоги из профиля: `username`, `role` (`created`, `admin`,
+//!   `moderator`, `member`), `blog`
+//! - `crawl_state` - состояние обхода блогов: `blog`, `next_page`, `finished`
+//!   (блог хотя бы раз пройден до конца, `next_page` тогда - страница, с
+//!   которой продолжить догоняющий обход)
+//!
+//! Полный текст схемы - [`SCHEMA`](constant.SCHEMA.html).
+
+extern crate rusqlite;
+extern crate hyper;
+
+use super::{TClient, TabunResult, TabunError, Post, Comment, UserInfo, TopicType, TopicLink, Photo, InBlogs};
+
+use rusqlite::Connection;
+use hyper::status::StatusCode;
+
+use std::collections::HashMap;
+use std::path::Path;
+
+///SQL, создающий таблицы архива, если их ещё нет
+pub const SCHEMA: &'static str = "
+CREATE TABLE IF NOT EXISTS posts (
+    id              INTEGER PRIMARY KEY,
+    blog            TEXT NOT NULL,
+    title           TEXT NOT NULL,
+    body            TEXT NOT NULL,
+    date            TEXT NOT NULL,
+    author          TEXT NOT NULL,
+    comments_count  INTEGER NOT NULL,
+    draft           INTEGER NOT NULL,
+    topic_type      TEXT NOT NULL,
+    link_url        TEXT,
+    link_clicks     INTEGER,
+    deleted         INTEGER NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS posts_blog ON posts (blog, id);
+
+CREATE TABLE IF NOT EXISTS post_tags (
+    post_id         INTEGER NOT NULL,
+    position        INTEGER NOT NULL,
+    tag             TEXT NOT NULL,
+    PRIMARY KEY (post_id, position)
+);
+
+CREATE TABLE IF NOT EXISTS photos (
+    post_id         INTEGER NOT NULL,
+    position        INTEGER NOT NULL,
+    url             TEXT NOT NULL,
+    description     TEXT NOT NULL,
+    PRIMARY KEY (post_id, position)
+);
+
+CREATE TABLE IF NOT EXISTS comments (
+    id              INTEGER PRIMARY KEY,
+    post_id         INTEGER NOT NULL,
+    parent          INTEGER NOT NULL,
+    author          TEXT NOT NULL,
+    date            TEXT NOT NULL,
+    votes           INTEGER NOT NULL,
+    body            TEXT NOT NULL,
+    deleted         INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS comments_post ON comments (post_id, id);
+
+CREATE TABLE IF NOT EXISTS users (
+    username        TEXT PRIMARY KEY,
+    id              INTEGER NOT NULL,
+    realname        TEXT NOT NULL,
+    skill           REAL NOT NULL,
+    rating          REAL NOT NULL,
+    userpic         TEXT NOT NULL,
+    description     TEXT NOT NULL,
+    publications    INTEGER NOT NULL,
+    favourites      INTEGER NOT NULL,
+    friends         INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS user_info (
+    username        TEXT NOT NULL,
+    key             TEXT NOT NULL,
+    value           TEXT NOT NULL,
+    PRIMARY KEY (username, key)
+);
+
+CREATE TABLE IF NOT EXISTS user_blogs (
+    username        TEXT NOT NULL,
+    role            TEXT NOT NULL,
+    blog            TEXT NOT NULL,
+    PRIMARY KEY (username, role, blog)
+);
+
+CREATE TABLE IF NOT EXISTS crawl_state (
+    blog            TEXT PRIMARY KEY,
+    next_page       INTEGER NOT NULL,
+    finished        INTEGER NOT NULL
+);
+";
+
+///Архив в базе SQLite
+pub struct Archive {
+    conn:   Connection,
+}
+
+///Что было сохранено за один обход
+#[derive(Debug,Clone,Default)]
+pub struct ArchiveStats {
+    ///Пройдено страниц списка постов
+    pub pages:      u32,
+    pub posts:      u32,
+    pub comments:   u32,
+
+    ///Сохранено новых профилей
+    pub users:      u32,
+
+    ///Авторы, чьи профили не удалось загрузить из-за ошибки, кроме 404
+    pub failed_users:   Vec<String>,
+}
+
+fn topic_type_name(typ: TopicType) -> &'static str {
+    match typ {
+        TopicType::Topic    => "topic",
+        TopicType::Question => "question",
+        TopicType::Link     => "link",
+        TopicType::Photoset => "photoset"
+    }
+}
+
+fn topic_type_from_name(name: &str) -> TopicType {
+    match name {
+        "question"  => TopicType::Question,
+        "link"      => TopicType::Link,
+        "photoset"  => TopicType::Photoset,
+        _           => TopicType::Topic
+    }
+}
+
+fn insert_post(conn: &Connection, blog: &str, post: &Post) -> rusqlite::Result<()> {
+    let id = post.id as i64;
+
+    try!(conn.execute("INSERT OR REPLACE INTO posts
+                       (id, blog, title, body, date, author, comments_count, draft, topic_type, link_url, link_clicks)
+                       VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        &[&id, &blog, &post.title, &post.body, &post.date, &post.author,
+          &(post.comments_count as i64), &post.draft, &topic_type_name(post.topic_type),
+          &post.link.as_ref().map(|x| x.url.clone()), &post.link.as_ref().map(|x| x.clicks as i64)]));
+
+    try!(conn.execute("DELETE FROM post_tags WHERE post_id = ?", &[&id]));
+    for (i, tag) in post.tags.iter().enumerate() {
+        try!(conn.execute("INSERT INTO post_tags (post_id, position, tag) VALUES (?, ?, ?)",
+            &[&id, &(i as i64), tag]));
+    }
+
+    try!(conn.execute("DELETE FROM photos WHERE post_id = ?", &[&id]));
+    for (i, photo) in post.photos.iter().enumerate() {
+        try!(conn.execute("INSERT INTO photos (post_id, position, url, description) VALUES (?, ?, ?, ?)",
+            &[&id, &(i as i64), &photo.url, &photo.description]));
+    }
+
+    Ok(())
+}
+
+fn insert_comment(conn: &Connection, comment: &Comment) -> rusqlite::Result<()> {
+    try!(conn.execute("INSERT OR REPLACE INTO comments
+                       (id, post_id, parent, author, date, votes, body, deleted)
+                       VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        &[&(comment.id as i64), &(comment.post_id as i64), &(comment.parent as i64), &comment.author,
+          &comment.date, &comment.votes, &comment.body, &comment.deleted]));
+    Ok(())
+}
+
+fn insert_user(conn: &Connection, user: &UserInfo) -> rusqlite::Result<()> {
+    try!(conn.execute("INSERT OR REPLACE INTO users
+                       (username, id, realname, skill, rating, userpic, description, publications, favourites, friends)
+                       VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        &[&user.username, &(user.id as i64), &user.realname, &(user.skill as f64), &(user.rating as f64),
+          &user.userpic, &user.description, &(user.publications as i64), &(user.favourites as i64),
+          &(user.friends as i64)]));
+
+    try!(conn.execute("DELETE FROM user_info WHERE username = ?", &[&user.username]));
+    for (key, value) in &user.other_info {
+        try!(conn.execute("INSERT INTO user_info (username, key, value) VALUES (?, ?, ?)",
+            &[&user.username, key, value]));
+    }
+
+    try!(conn.execute("DELETE FROM user_blogs WHERE username = ?", &[&user.username]));
+    let roles = [
+        ("created",     &user.blogs.created),
+        ("admin",       &user.blogs.admin),
+        ("moderator",   &user.blogs.moderator),
+        ("member",      &user.blogs.member)
+    ];
+    for &(role, blogs) in &roles {
+        for blog in blogs {
+            try!(conn.execute("INSERT OR IGNORE INTO user_blogs (username, role, blog) VALUES (?, ?, ?)",
+                &[&user.username, &role, blog]));
+        }
+    }
+
+    Ok(())
+}
+
+impl Archive {
+    ///Открывает архив, создавая файл и таблицы при необходимости
+    pub fn open<P: AsRef<Path>>(path: P) -> TabunResult<Archive> {
+        let conn = try!(Connection::open(path));
+        try!(conn.execute_batch(SCHEMA));
+        Ok(Archive{ conn: conn })
+    }
+
+    ///Соединение с базой, например для своих запросов
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    ///Есть ли пост в архиве
+    pub fn has_post(&self, post_id: u32) -> TabunResult<bool> {
+        let count: i64 = try!(self.conn.query_row("SELECT COUNT(*) FROM posts WHERE id = ?",
+            &[&(post_id as i64)], |row| row.get(0)));
+        Ok(count > 0)
+    }
+
+    ///Блог, в котором лежит пост в архиве, None - если поста там нет
+    pub fn post_blog(&self, post_id: u32) -> TabunResult<Option<String>> {
+        match self.conn.query_row("SELECT blog FROM posts WHERE id = ?", &[&(post_id as i64)], |row| row.get(0)) {
+            Ok(x)                                       => Ok(Some(x)),
+            Err(rusqlite::Error::QueryReturnedNoRows)   => Ok(None),
+            Err(x)                                      => Err(TabunError::from(x))
+        }
+    }
+
+    ///Есть ли профиль пользователя в архиве
+    pub fn has_user(&self, username: &str) -> TabunResult<bool> {
+        let count: i64 = try!(self.conn.query_row("SELECT COUNT(*) FROM users WHERE username = ?",
+            &[&username], |row| row.get(0)));
+        Ok(count > 0)
+    }
+
+    ///Выполняет `f` одной транзакцией: если `f` вернёт ошибку, ничего из
+    ///записанного в ней не сохранится. Транзакции могут быть вложенными,
+    ///так что внутри можно вызывать `save_post` и остальные методы
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///# let mut archive = libtabun::archive::Archive::open(":memory:").unwrap();
+    ///let post = user.get_post("news", 157198).unwrap();
+    ///let old = user.get_post("news", 157197).unwrap();
+    ///archive.transaction(|a| {
+    ///    try!(a.save_post("news", &post, &[]));
+    ///    a.set_post_deleted(old.id, true)
+    ///}).unwrap();
+    ///```
+    pub fn transaction<T, F>(&mut self, f: F) -> TabunResult<T> where F: FnOnce(&mut Archive) -> TabunResult<T> {
+        // SAVEPOINT, в отличие от BEGIN, можно вкладывать
+        try!(self.conn.execute_batch("SAVEPOINT archive"));
+
+        match f(self) {
+            Ok(x)   => {
+                try!(self.conn.execute_batch("RELEASE archive"));
+                Ok(x)
+            },
+            Err(x)  => {
+                let _ = self.conn.execute_batch("ROLLBACK TO archive; RELEASE archive");
+                Err(x)
+            }
+        }
+    }
+
+    ///Сохраняет пост вместе с комментами одной транзакцией,
+    ///заменяя старую версию, если она была
+    pub fn save_post(&mut self, blog: &str, post: &Post, comments: &[Comment]) -> TabunResult<()> {
+        self.transaction(|archive| {
+            try!(insert_post(&archive.conn, blog, post));
+            for comment in comments {
+                try!(insert_comment(&archive.conn, comment));
+            }
+            Ok(())
+        })
+    }
+
+    ///Сохраняет комменты одной транзакцией, не трогая пост
+    pub fn save_comments(&mut self, comments: &[Comment]) -> TabunResult<()> {
+        self.transaction(|archive| {
+            for comment in comments {
+                try!(insert_comment(&archive.conn, comment));
+            }
+            Ok(())
+        })
+    }
+
+    ///Сохраняет профиль пользователя, заменяя старый
+    pub fn save_user(&mut self, user: &UserInfo) -> TabunResult<()> {
+        self.transaction(|archive| {
+            try!(insert_user(&archive.conn, user));
+            Ok(())
+        })
+    }
+
+    ///Помечает пост пропавшим из блога или снимает пометку. Пост остаётся
+    ///в архиве, но не попадает в [`load_posts`](#method.load_posts).
+    ///Повторное сохранение поста пометку снимает
+    pub fn set_post_deleted(&self, post_id: u32, deleted: bool) -> TabunResult<()> {
+        try!(self.conn.execute("UPDATE posts SET deleted = ? WHERE id = ?", &[&deleted, &(post_id as i64)]));
+        Ok(())
+    }
+
+    ///Пост из архива с тэгами и фотографиями, None - если его там нет
+    pub fn load_post(&self, post_id: u32) -> TabunResult<Option<Post>> {
+        let id = post_id as i64;
+
+        let res = self.conn.query_row(
+            "SELECT title, body, date, author, comments_count, draft, topic_type, link_url, link_clicks
+             FROM posts WHERE id = ?",
+            &[&id],
+            |row| Post{
+                title:          row.get(0),
+                body:           row.get(1),
+                date:           row.get(2),
+                tags:           Vec::new(),
+                favourite_tags: Vec::new(),
+                comments_count: row.get::<_, i64>(4) as u32,
+                author:         row.get(3),
+                id:             post_id,
+                draft:          row.get(5),
+                has_cut:        false,
+                topic_type:     topic_type_from_name(&row.get::<_, String>(6)),
+                link:           row.get::<_, Option<String>>(7).map(|url| TopicLink{
+                    url:    url,
+                    clicks: row.get::<_, Option<i64>>(8).unwrap_or(0) as u32
+                }),
+                photos:         Vec::new(),
+            });
+
+        let mut post = match res {
+            Ok(x)                                       => x,
+            Err(rusqlite::Error::QueryReturnedNoRows)   => return Ok(None),
+            Err(x)                                      => return Err(TabunError::from(x))
+        };
+
+        let mut stmt = try!(self.conn.prepare("SELECT tag FROM post_tags WHERE post_id = ? ORDER BY position"));
+        for tag in try!(stmt.query_map(&[&id], |row| row.get::<_, String>(0))) {
+            post.tags.push(try!(tag));
+        }
+
+        let mut stmt = try!(self.conn.prepare("SELECT url, description FROM photos WHERE post_id = ? ORDER BY position"));
+        for photo in try!(stmt.query_map(&[&id], |row| Photo{ url: row.get(0), description: row.get(1) })) {
+            post.photos.push(try!(photo));
+        }
+
+        Ok(Some(post))
+    }
+
+    ///Посты блога из архива от новых к старым, начиная с `offset`,
+    ///и общее кол-во постов блога в архиве. Пропавшие из блога посты
+    ///не учитываются
+    pub fn load_posts(&self, blog: &str, offset: u32, limit: u32) -> TabunResult<(Vec<Post>, u32)> {
+        let total: i64 = try!(self.conn.query_row("SELECT COUNT(*) FROM posts WHERE blog = ? AND deleted = 0",
+            &[&blog], |row| row.get(0)));
+
+        let mut ids = Vec::new();
+        {
+            let mut stmt = try!(self.conn.prepare(
+                "SELECT id FROM posts WHERE blog = ? AND deleted = 0 ORDER BY id DESC LIMIT ? OFFSET ?"));
+            for id in try!(stmt.query_map(&[&blog, &(limit as i64), &(offset as i64)], |row| row.get::<_, i64>(0))) {
+                ids.push(try!(id) as u32);
+            }
+        }
+
+        let mut posts = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(post) = try!(self.load_post(id)) {
+                posts.push(post);
+            }
+        }
+
+        Ok((posts, total as u32))
+    }
+
+    ///Комменты поста из архива в виде HashMap ID-Коммент
+    pub fn load_comments(&self, post_id: u32) -> TabunResult<HashMap<u32, Comment>> {
+        let mut stmt = try!(self.conn.prepare(
+            "SELECT id, parent, author, date, votes, body, deleted FROM comments WHERE post_id = ?"));
+
+        let mut ret = HashMap::new();
+        for comment in try!(stmt.query_map(&[&(post_id as i64)], |row| Comment{
+            body:       row.get(5),
+            id:         row.get::<_, i64>(0) as u32,
+            author:     row.get(2),
+            date:       row.get(3),
+            votes:      row.get(4),
+            parent:     row.get::<_, i64>(1) as u32,
+            post_id:    post_id,
+            deleted:    row.get(6),
+        })) {
+            let comment = try!(comment);
+            ret.insert(comment.id, comment);
+        }
+
+        Ok(ret)
+    }
+
+    ///Профиль пользователя из архива, None - если его там нет
+    pub fn load_user(&self, username: &str) -> TabunResult<Option<UserInfo>> {
+        let res = self.conn.query_row(
+            "SELECT username, id, realname, skill, rating, userpic, description, publications, favourites, friends
+             FROM users WHERE username = ?",
+            &[&username],
+            |row| UserInfo{
+                username:       row.get(0),
+                realname:       row.get(2),
+                skill:          row.get::<_, f64>(3) as f32,
+                id:             row.get::<_, i64>(1) as u32,
+                rating:         row.get::<_, f64>(4) as f32,
+                userpic:        row.get(5),
+                description:    row.get(6),
+                other_info:     HashMap::new(),
+                blogs:          InBlogs{
+                    created:    Vec::new(),
+                    admin:      Vec::new(),
+                    moderator:  Vec::new(),
+                    member:     Vec::new()
+                },
+                publications:   row.get::<_, i64>(7) as u32,
+                favourites:     row.get::<_, i64>(8) as u32,
+                friends:        row.get::<_, i64>(9) as u32
+            });
+
+        let mut user = match res {
+            Ok(x)                                       => x,
+            Err(rusqlite::Error::QueryReturnedNoRows)   => return Ok(None),
+            Err(x)                                      => return Err(TabunError::from(x))
+        };
+
+        let mut stmt = try!(self.conn.prepare("SELECT key, value FROM user_info WHERE username = ?"));
+        for pair in try!(stmt.query_map(&[&username], |row| (row.get::<_, String>(0), row.get::<_, String>(1)))) {
+            let (key, value) = try!(pair);
+            user.other_info.insert(key, value);
+        }
+
+        let mut stmt = try!(self.conn.prepare("SELECT role, blog FROM user_blogs WHERE username = ? ORDER BY rowid"));
+        for pair in try!(stmt.query_map(&[&username], |row| (row.get::<_, String>(0), row.get::<_, String>(1)))) {
+            let (role, blog) = try!(pair);
+            match role.as_str() {
+                "created"   => user.blogs.created.push(blog),
+                "admin"     => user.blogs.admin.push(blog),
+                "moderator" => user.blogs.moderator.push(blog),
+                _           => user.blogs.member.push(blog)
+            }
+        }
+
+        Ok(Some(user))
+    }
+
+    ///Страница, с которой нужно продолжить обход блога, и был ли блог уже
+    ///пройден до конца
+    fn next_page(&self, blog: &str) -> TabunResult<(u32, bool)> {
+        match self.conn.query_row("SELECT next_page, finished FROM crawl_state WHERE blog = ?",
+                                  &[&blog], |row| (row.get::<_, i64>(0), row.get::<_, bool>(1))) {
+            Ok((page, finished))                            => Ok((page as u32, finished)),
+            Err(rusqlite::Error::QueryReturnedNoRows)       => Ok((1, false)),
+            Err(x)                                          => Err(TabunError::from(x))
+        }
+    }
+
+    fn set_state(&self, blog: &str, next_page: u32, finished: bool) -> TabunResult<()> {
+        try!(self.conn.execute("INSERT OR REPLACE INTO crawl_state (blog, next_page, finished) VALUES (?, ?, ?)",
+            &[&blog, &(next_page as i64), &finished]));
+        Ok(())
+    }
+
+    ///Сохраняет профили тех из `authors`, кого ещё нет в архиве. Удалённые
+    ///профили пропускаются, а те, что не удалось загрузить, записываются
+    ///в `stats.failed_users`
+    fn archive_users(&mut self, client: &mut TClient, authors: &[String], stats: &mut ArchiveStats) -> TabunResult<()> {
+        for author in authors {
+            if author.is_empty() || stats.failed_users.contains(author) || try!(self.has_user(author)) {
+                continue;
+            }
+
+            match client.get_profile(author.as_str()) {
+                Ok(user)                                            => {
+                    try!(self.save_user(&user));
+                    stats.users += 1;
+                },
+                Err(TabunError::NumError(StatusCode::NotFound))    => {},
+                Err(_)                                              => stats.failed_users.push(author.clone())
+            }
+        }
+
+        Ok(())
+    }
+
+    ///Обходит блог и сохраняет посты, которых ещё нет в архиве, с комментами
+    ///и профилями авторов. Если обход прервался с ошибкой, его можно
+    ///запустить снова, он продолжится с той же страницы. Уже пройденный
+    ///до конца блог обходится только до первой страницы без новых постов
+    pub fn archive_blog(&mut self, client: &mut TClient, blog: &str) -> TabunResult<ArchiveStats> {
+        let mut stats = ArchiveStats::default();
+        let (mut page, finished) = try!(self.next_page(blog));
+
+        loop {
+            let list = try!(client.get_posts(blog, page));
+            stats.pages += 1;
+
+            let mut new_posts = 0;
+            for item in &list.items {
+                if try!(self.has_post(item.id)) {
+                    continue;
+                }
+                new_posts += 1;
+
+                let post = try!(client.get_post(blog, item.id));
+                let mut comments = try!(client.get_comments(format!("/blog/{}/{}.html", blog, item.id).as_str()))
+                    .into_iter()
+                    .map(|(_, x)| x)
+                    .collect::<Vec<_>>();
+                comments.sort_by_key(|x| x.id);
+
+                let mut authors = vec![post.author.clone()];
+                for comment in &comments {
+                    if !authors.contains(&comment.author) {
+                        authors.push(comment.author.clone());
+                    }
+                }
+                try!(self.archive_users(client, &authors, &mut stats));
+
+                try!(self.save_post(blog, &post, &comments));
+                stats.posts += 1;
+                stats.comments += comments.len() as u32;
+            }
+
+            // Догоняющий обход дальше первой страницы без новых постов не идёт:
+            // всё, что старше, уже было сохранено
+            if list.items.is_empty() || list.next.is_none() || (finished && new_posts == 0) {
+                try!(self.set_state(blog, 1, true));
+                break;
+            }
+
+            page += 1;
+            try!(self.set_state(blog, page, finished));
+        }
+
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::{Comment, TopicType, TabunResult, TabunError};
+    use ::test::post;
+
+    #[test]
+    fn test_save_post() {
+        let mut archive = Archive::open(":memory:").unwrap();
+        let comment = Comment{
+            body:       "Коммент".to_string(),
+            id:         10,
+            author:     "Orhideous".to_string(),
+            date:       "2016-09-01T12:00:00+03:00".to_string(),
+            votes:      2,
+            parent:     0,
+            post_id:    1,
+            deleted:    false,
+        };
+
+        assert!(!archive.has_post(1).unwrap());
+        archive.save_post("news", &post(1), &[comment.clone()]).unwrap();
+        // Повторное сохранение заменяет пост, а не дублирует
+        archive.save_post("news", &post(1), &[comment]).unwrap();
+        assert!(archive.has_post(1).unwrap());
+
+        let count: i64 = archive.connection().query_row("SELECT COUNT(*) FROM post_tags", &[], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+
+        let loaded = archive.load_post(1).unwrap().unwrap();
+        assert_eq!(loaded.tags, vec!["тэг".to_string()]);
+        assert_eq!(loaded.topic_type, TopicType::Topic);
+        assert_eq!(archive.load_comments(1).unwrap()[&10].votes, 2);
+        assert!(archive.load_post(2).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_transaction() {
+        let mut archive = Archive::open(":memory:").unwrap();
+
+        let res: TabunResult<()> = archive.transaction(|a| {
+            try!(a.save_post("news", &post(1), &[]));
+            Err(TabunError::Error("Ошибка".to_string(), "откат".to_string()))
+        });
+        assert!(res.is_err());
+        assert!(!archive.has_post(1).unwrap());
+
+        archive.transaction(|a| a.save_post("news", &post(1), &[])).unwrap();
+        archive.save_post("news", &post(2), &[]).unwrap();
+        archive.set_post_deleted(1, true).unwrap();
+
+        let (posts, total) = archive.load_posts("news", 0, 10).unwrap();
+        assert_eq!((posts.len(), total), (1, 1));
+        assert_eq!(posts[0].id, 2);
+        assert!(archive.load_post(1).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_crawl_state() {
+        let archive = Archive::open(":memory:").unwrap();
+        assert_eq!(archive.next_page("news").unwrap(), (1, false));
+
+        archive.set_state("news", 5, false).unwrap();
+        assert_eq!(archive.next_page("news").unwrap(), (5, false));
+
+        archive.set_state("news", 1, true).unwrap();
+        assert_eq!(archive.next_page("news").unwrap(), (1, true));
+
+        // Прерванный догоняющий обход продолжается со своей страницы
+        archive.set_state("news", 3, true).unwrap();
+        assert_eq!(archive.next_page("news").unwrap(), (3, true));
+    }
+}
@@ -0,0 +1,87 @@
+/* Blogs list
+ *
+ * Copyright (C) 2016 TyanNN <TyanNN@cocaine.ninja>
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along
+ * with this program; if not, write to the Free Software Foundation, Inc.,
+ * 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+*/
+
+extern crate select;
+
+use super::*;
+
+use select::document::Document;
+use select::predicate::{Class, Name, And};
+
+impl<'a> TClient<'a> {
+
+    ///Получает страницу списка блогов
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let blogs = user.get_blogs(1).unwrap();
+    ///for blog in &blogs.items {
+    ///    println!("{} ({}) - {} читателей", blog.title, blog.name, blog.readers);
+    ///}
+    ///```
+    pub fn get_blogs(&mut self, page: u32) -> TabunResult<Page<BlogItem>> {
+        let doc = try!(self.get_document(&format!("/blogs/page{}/", page)));
+        let blogs = try!(self.doc_get_blogs(&doc));
+        Ok(self.doc_get_page(&doc, blogs))
+    }
+
+    ///Все блоги, страницы загружаются по мере перебора
+    pub fn blogs<'c>(&'c mut self) -> Listing<'c, 'a, BlogItem> {
+        Listing::new(self, Box::new(|page| format!("/blogs/page{}/", page)), TClient::doc_get_blogs)
+    }
+
+    pub fn doc_get_blogs(&mut self, doc: &Document) -> TabunResult<Vec<BlogItem>> {
+        let mut ret = Vec::new();
+
+        for row in doc.find(Class("table-blogs")).find(Name("tbody")).find(Name("tr")).iter() {
+            let link = try_to_parse!(row.find(And(Name("a"), Class("blog-name"))).first());
+
+            let name = try_to_parse!(hado!{
+                href <- link.attr("href");
+                href.trim_right_matches('/').rsplit('/').next()
+            }).to_string();
+
+            let readers = try_to_parse!(hado!{
+                cell <- row.find(Class("cell-readers")).first();
+                cell.text().trim().parse::<u32>().ok()
+            });
+
+            let rating = try_to_parse!(hado!{
+                cell <- row.find(Class("cell-rating")).first();
+                cell.text().trim().replace(',', ".").parse::<f32>().ok()
+            });
+
+            let avatar = row.find(Name("img")).first()
+                .and_then(|x| x.attr("src"))
+                .unwrap_or("")
+                .to_string();
+
+            ret.push(BlogItem{
+                name:       name,
+                title:      link.text().trim().to_string(),
+                readers:    readers,
+                rating:     rating,
+                avatar:     avatar,
+            });
+        }
+
+        Ok(ret)
+    }
+}
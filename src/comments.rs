@@ -28,6 +28,16 @@ use select::predicate::{And,Class,Name};
 use std::collections::HashMap;
 use regex::Regex;
 
+///Комменты из ленты в порядке от новых к старым
+fn feed_comments(client: &mut TClient, doc: &Document) -> TabunResult<Vec<Comment>> {
+    let mut ret = try!(client.doc_get_comments(doc, None::<&str>))
+        .into_iter()
+        .map(|(_, x)| x)
+        .collect::<Vec<_>>();
+    ret.sort_by(|a, b| b.id.cmp(&a.id));
+    Ok(ret)
+}
+
 impl<'a> TClient<'a> {
 
     ///Получить комменты из некоторого поста/сообщения
@@ -55,6 +65,33 @@ impl<'a> TClient<'a> {
         self.doc_get_comments(&doc, url.as_str())
     }
 
+    ///Получить страницу ленты комментов, от новых к старым
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let feed = user.get_comments_feed(2).unwrap();
+    ///println!("{} комментов, страница {} из {}", feed.items.len(), feed.page, feed.total_pages);
+    ///```
+    pub fn get_comments_feed(&mut self, page: u32) -> TabunResult<Page<Comment>> {
+        let doc = try!(self.get_document(&format!("/comments/page{}/", page)));
+        let comments = try!(feed_comments(self, &doc));
+        Ok(self.doc_get_page(&doc, comments))
+    }
+
+    ///Вся лента комментов, страницы загружаются по мере перебора
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///for comment in user.comments_feed().pages(3) {
+    ///    println!("{}", comment.unwrap().author);
+    ///}
+    ///```
+    pub fn comments_feed<'c>(&'c mut self) -> Listing<'c, 'a, Comment> {
+        Listing::new(self, Box::new(|page| format!("/comments/page{}/", page)), feed_comments)
+    }
+
     ///Получить комменты из некоторого поста/сообщения
     ///в виде HashMap ID-Коммент. Если ссылка указана как None,
     ///то получает из `/comments/`. Строку url требуется передать для
@@ -162,7 +199,8 @@ impl<'a> TClient<'a> {
     }
 
     ///Оставить коммент к какому-нибудь посту, reply=0 - ответ на сам пост,
-    ///иначе на чей-то коммент, возвращает ID нового коммента
+    ///иначе на чей-то коммент, возвращает ID нового коммента. Посмотреть,
+    ///как коммент будет выглядеть, можно через [`preview_text`](#method.preview_text)
     ///
     ///# Examples
     ///```no_run
@@ -224,6 +262,19 @@ impl<'a> TClient<'a> {
     pub fn favourite_comment(&mut self, id: u32, typ: bool) -> TabunResult<u32> {
         self.favourite(id, typ, true)
     }
+
+    ///Задаёт тэги избранного для комментария, который уже в избранном,
+    ///возвращает сохранённые тэги
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///user.favourite_comment(12345, true).unwrap();
+    ///user.set_favourite_comment_tags(12345, &["смешное"]);
+    ///```
+    pub fn set_favourite_comment_tags(&mut self, id: u32, tags: &[&str]) -> TabunResult<Vec<String>> {
+        self.set_favourite_tags(id, tags, true)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,132 @@
+/* Favourites
+ *
+ * Copyright (C) 2016 TyanNN <TyanNN@cocaine.ninja>
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along
+ * with this program; if not, write to the Free Software Foundation, Inc.,
+ * 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+*/
+
+extern crate select;
+extern crate url;
+
+use super::*;
+
+use select::document::Document;
+use select::predicate::{Name,Class,And};
+
+use url::percent_encoding::{utf8_percent_encode, PATH_SEGMENT_ENCODE_SET};
+
+impl<'a> TClient<'a> {
+
+    ///Получает избранные посты пользователя, если тэг указан,
+    ///то только посты с этим тэгом избранного
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///user.get_favourite_posts("Orhideous", None, 1);
+    /// //или
+    ///user.get_favourite_posts("Orhideous", "музыка", 1);
+    ///```
+    pub fn get_favourite_posts<'f, T: Into<Option<&'f str>>>(&mut self, login: &str, tag: T, page: u32) -> TabunResult<Page<Post>> {
+        let url = match tag.into() {
+            None    => format!("/profile/{}/favourites/topics/page{}/", login, page),
+            Some(x) => format!("/profile/{}/favourites/topics/tag/{}/page{}/",
+                               login, utf8_percent_encode(x, PATH_SEGMENT_ENCODE_SET), page)
+        };
+
+        let doc = try!(self.get_document(&url));
+        let posts = try!(self.doc_get_posts(&doc));
+        Ok(self.doc_get_page(&doc, posts))
+    }
+
+    ///Получает избранные комментарии пользователя
+    ///от новых к старым
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///user.get_favourite_comments("Orhideous", 1);
+    ///```
+    pub fn get_favourite_comments(&mut self, login: &str, page: u32) -> TabunResult<Page<Comment>> {
+        let doc = try!(self.get_document(&format!("/profile/{}/favourites/comments/page{}/", login, page)));
+
+        let mut comments = try!(self.doc_get_comments(&doc, None)).into_iter()
+            .map(|(_, x)| x)
+            .collect::<Vec<_>>();
+        comments.sort_by(|a, b| b.id.cmp(&a.id));
+
+        Ok(self.doc_get_page(&doc, comments))
+    }
+
+    ///Получает тэги избранного пользователя вместе с количеством их
+    ///использований из облака тэгов
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///for tag in user.get_favourite_tags("Orhideous").unwrap() {
+    ///    println!("{} ({})", tag.name, tag.count);
+    ///    let posts = user.get_favourite_posts("Orhideous", tag.name.as_str(), 1);
+    ///}
+    ///```
+    pub fn get_favourite_tags(&mut self, login: &str) -> TabunResult<Vec<FavouriteTag>> {
+        let doc = try!(self.get_document(&format!("/profile/{}/favourites/topics/", login)));
+        self.doc_get_favourite_tags(&doc)
+    }
+
+    pub fn doc_get_favourite_tags(&self, doc: &Document) -> TabunResult<Vec<FavouriteTag>> {
+        let mut ret: Vec<FavouriteTag> = Vec::new();
+
+        for a in doc.find(And(Name("ul"),Class("tag-cloud"))).find(Name("a")).iter() {
+            let href = try_to_parse!(a.attr("href"));
+            if !href.contains("/favourites/topics/tag/") {
+                continue
+            }
+
+            let name = a.text().trim().to_string();
+            if ret.iter().any(|x| x.name == name) {
+                continue
+            }
+
+            // Количество Табун выводит во всплывающей подсказке, но бывает, что её нет
+            let count = a.attr("title").and_then(|x| x.trim().parse::<u32>().ok()).unwrap_or(0);
+
+            ret.push(FavouriteTag{
+                name:   name,
+                count:  count
+            });
+        }
+
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::TClient;
+
+    #[test]
+    fn test_get_favourite_posts() {
+        let mut user = TClient::new(None,None).unwrap();
+        match user.get_favourite_posts("Orhideous", None, 1) {
+            Ok(x)   => {
+                for post in &x.items {
+                    assert!(post.id > 0);
+                }
+            },
+            Err(x)  => panic!(x)
+        }
+    }
+}
@@ -0,0 +1,102 @@
+/* Personal feed
+ *
+ * Copyright (C) 2016 TyanNN <TyanNN@cocaine.ninja>
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along
+ * with this program; if not, write to the Free Software Foundation, Inc.,
+ * 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+*/
+
+extern crate select;
+
+use super::*;
+
+use select::document::Document;
+
+impl<'a> TClient<'a> {
+
+    ///Получает посты из личной ленты (`/feed/`). Если `before` указан как None,
+    ///то получает первую страницу ленты, иначе — посты старше поста с указанным ID
+    ///(так лента листается на самом сайте). Номеров страниц у ленты нет,
+    ///поэтому в [`Page`](struct.Page.html) страница всегда одна.
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let feed = user.get_feed(None).unwrap();
+    ///if let Some(last) = feed.items.last() {
+    ///    let older = user.get_feed(last.id).unwrap();
+    ///}
+    ///```
+    pub fn get_feed<T: Into<Option<u32>>>(&mut self, before: T) -> TabunResult<Page<Post>> {
+        match before.into() {
+            None => {
+                let doc = try!(self.get_document("/feed/"));
+                let posts = try!(self.doc_get_posts(&doc));
+                Ok(self.doc_get_page(&doc, posts))
+            },
+            Some(id) => {
+                let id = id.to_string();
+
+                let data = try!(self.ajax(
+                    "/feed/get_more/",
+                    &vec![("last_id", id.as_str())]
+                ));
+
+                let text = match get_json!(data, "/result", as_str) {
+                    Some(x) => x,
+                    None => return Err(parse_error!("Server did not return result"))
+                };
+
+                let doc = Document::from(text);
+                let posts = try!(self.doc_get_posts(&doc));
+                Ok(self.doc_get_page(&doc, posts))
+            }
+        }
+    }
+
+    ///Подписаться/отписаться от блога или пользователя в личной ленте
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let blog_id = user.get_blog_id("computers").unwrap();
+    ///user.feed_subscribe(libtabun::FeedTarget::Blog, blog_id, true);
+    ///```
+    pub fn feed_subscribe(&mut self, target: FeedTarget, id: u32, subscribed: bool) -> TabunResult<()> {
+        let id = id.to_string();
+
+        let body = vec![
+            ("type",    match target { FeedTarget::Blog => "blogs", FeedTarget::User => "users" }),
+            ("id",      id.as_str())
+        ];
+
+        try!(self.ajax(
+            if subscribed { "/feed/subscribe/" } else { "/feed/unsubscribe/" },
+            &body
+        ));
+        Ok(())
+    }
+
+    ///Подписаться в личной ленте на пользователя по его логину
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///user.feed_subscribe_by_login("Orhideous");
+    ///```
+    pub fn feed_subscribe_by_login(&mut self, login: &str) -> TabunResult<()> {
+        try!(self.ajax("/feed/subscribeByLogin/", &vec![("login", login)]));
+        Ok(())
+    }
+}
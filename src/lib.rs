@@ -32,11 +32,29 @@
 //! ```no_run
 //! let mut user = libtabun::TClient::new("username", "password").unwrap();
 //! let posts = user.get_posts("fanart", 1).unwrap();
-//! for post in &posts {
+//! for post in &posts.items {
 //!     println!("{} - {}", post.id, post.title);
 //! }
 //! ```
 //!
+//! # Сериализация
+//!
+//! С фичей `serialize` структуры с данными (`Post`, `Comment`, `UserInfo`,
+//! `Talk` и прочие) реализуют `Serialize` и `Deserialize` из serde.
+//! Поля в JSON называются так же, как в структурах, `Option` без значения
+//! превращается в `null`, типы постов и пол - строки в нижнем регистре
+//! (`"topic"`, `"question"`, `"link"`, `"photoset"`; `"male"`, `"female"`, `"other"`),
+//! а ключи `Talk.comments` - ID комментов строками. Например, пост выглядит так:
+//!
+//! ```json
+//! {
+//!   "title": "Заголовок", "body": "Текст <b>поста</b>", "date": "2016-09-01T12:00:00+03:00",
+//!   "tags": ["тэг"], "favourite_tags": [], "comments_count": 3, "author": "Orhideous",
+//!   "id": 157198, "draft": false, "has_cut": false, "topic_type": "link",
+//!   "link": {"url": "https://example.com", "clicks": 42}, "photos": []
+//! }
+//! ```
+//!
 //! Можно использовать [`TClientBuilder`](struct.TClientBuilder.html) для
 //! большей кастомизации:
 //!
@@ -59,7 +77,10 @@ extern crate url;
 extern crate multipart;
 extern crate unescape;
 extern crate serde_json;
+extern crate sha1;
 #[macro_use] extern crate hado;
+#[cfg(feature = "serialize")] #[macro_use] extern crate serde_derive;
+#[cfg(feature = "archive")] extern crate rusqlite;
 
 use std::fmt::Display;
 use std::str::FromStr;
@@ -67,6 +88,7 @@ use std::str::FromStr;
 use regex::Regex;
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use hyper::client::Client;
 use hyper::client::request::Request;
@@ -86,6 +108,27 @@ use serde_json::Value;
 mod comments;
 mod posts;
 mod talks;
+mod wall;
+mod settings;
+mod feed;
+mod favourites;
+mod search;
+mod photoset;
+mod post_builder;
+mod listing;
+mod people;
+mod blogs;
+pub mod markup;
+pub mod markdown;
+pub mod render;
+pub mod media;
+pub mod store;
+pub mod reader;
+#[cfg(feature = "archive")] pub mod archive;
+#[cfg(feature = "archive")] pub mod sync;
+
+pub use post_builder::{PostBuilder, PostDraft, PostValidationError};
+pub use listing::{Listing, ListingIter};
 
 //Перечисления
 
@@ -109,7 +152,17 @@ pub enum TabunError {
     ///Ошибка парсинга страницы. Скорее всего будет возникать после изменения
     ///вёрстки Табуна, поэтому имеет смысл сообщать об этой ошибке
     ///разработчикам
-    ParseError(String, u32, String)
+    ParseError(String, u32, String),
+
+    ///Табун не принял отправленную форму (поста, опроса, личного сообщения).
+    ///Содержит сообщения об ошибках со страницы в виде пар заголовок-текст,
+    ///обычно по одному на каждое неправильно заполненное поле. Список пуст,
+    ///если Табун не объяснил причину
+    ValidationError(Vec<(String,String)>),
+
+    ///Ошибка базы данных [архива](archive/index.html)
+    #[cfg(feature = "archive")]
+    DatabaseError(rusqlite::Error)
 }
 
 ///Тип комментария для ответа
@@ -121,6 +174,80 @@ pub enum CommentType {
     Talk
 }
 
+///Тип поста
+#[derive(Debug,Clone,Copy,PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(rename_all = "lowercase"))]
+pub enum TopicType {
+    ///Обычный пост
+    Topic,
+
+    ///Опрос
+    Question,
+
+    ///Пост-ссылка
+    Link,
+
+    ///Фотосет
+    Photoset
+}
+
+///Период для рейтинговых разделов главной страницы
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Period {
+    ///За 24 часа
+    Day,
+
+    ///За 7 дней
+    Week,
+
+    ///За 30 дней
+    Month,
+
+    ///За всё время
+    All
+}
+
+///Раздел главной страницы
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum IndexFilter {
+    ///Хорошие
+    Good,
+
+    ///Новые за последние сутки
+    New,
+
+    ///Все новые
+    NewAll,
+
+    ///Обсуждаемые за указанный период
+    Discussed(Period),
+
+    ///Лучшие за указанный период
+    Top(Period)
+}
+
+///На что подписываться в личной ленте
+pub enum FeedTarget {
+    ///На новые посты в блоге
+    Blog,
+
+    ///На новые посты пользователя
+    User
+}
+
+///Пол в настройках профиля
+#[derive(Debug,Clone,PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serialize", serde(rename_all = "lowercase"))]
+pub enum Gender {
+    Male,
+    Female,
+
+    ///Не указан
+    Other
+}
+
 ///Тип данных для отправки multipart-запросом
 pub enum MultipartValue<'a> {
     Text(&'a str),
@@ -135,7 +262,7 @@ pub struct TClient<'a> {
     pub name:               String,
     pub security_ls_key:    String,
     pub host:               String,
-    client:                 Client,
+    client:                 Arc<Client>,
     cookies:                CookieJar<'a>,
 }
 
@@ -152,6 +279,7 @@ pub struct TClientBuilder {
 }
 
 #[derive(Debug,Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Comment {
     pub body:       String,
     pub id:         u32,
@@ -164,25 +292,80 @@ pub struct Comment {
 }
 
 #[derive(Debug,Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Post {
     pub title:          String,
     pub body:           String,
     pub date:           String,
     pub tags:           Vec<String>,
+
+    ///Тэги, которыми текущий пользователь пометил пост в избранном
+    pub favourite_tags: Vec<String>,
     pub comments_count: u32,
     pub author:         String,
     pub id:             u32,
+
+    ///Пост лежит в черновиках
+    pub draft:          bool,
+
+    ///Текст обрезан катом: в списках постов в `body` только вступление,
+    ///полный текст можно получить через `get_post`
+    pub has_cut:        bool,
+
+    pub topic_type:     TopicType,
+
+    ///Ссылка, если это пост-ссылка
+    pub link:           Option<TopicLink>,
+
+    ///Фотографии, если это фотосет. Табун показывает в посте
+    ///только первую порцию фотографий
+    pub photos:         Vec<Photo>,
+}
+
+///Ссылка из поста-ссылки
+#[derive(Debug,Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct TopicLink {
+    pub url:    String,
+
+    ///Кол-во переходов по ссылке
+    pub clicks: u32,
+}
+
+///Фотография из фотосета
+#[derive(Debug,Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Photo {
+    pub url:            String,
+    pub description:    String,
 }
 
+///Пост со страницы редактирования, содержит всё, что нужно,
+///чтобы отправить его обратно без изменений
 #[derive(Debug,Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct EditablePost {
     pub title:          String,
     pub body:           String,
     pub tags:           Vec<String>,
+
+    ///ID блога, в котором находится пост
+    pub blog_id:        u32,
+
+    ///Запрещено ли комментировать пост
+    pub forbid_comment: bool,
+    pub topic_type:     TopicType,
+
+    ///Ссылка для поста-ссылки, для остальных типов пустая
+    pub link_url:       String,
+
+    ///false - пост в черновиках
+    pub publish:        bool,
 }
 
 ///Блоги из списка блогов в [профиле](struct.UserInfo.html)
 #[derive(Debug,Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct InBlogs {
     ///Созданные пользователем блоги
     pub created: Vec<String>,
@@ -200,6 +383,7 @@ pub struct InBlogs {
 
 ///Профиль некоторого пользователя
 #[derive(Debug,Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct UserInfo {
     pub username:       String,
     pub realname:       String,
@@ -234,6 +418,7 @@ pub struct UserInfo {
 
 ///Диалог в личных сообщениях
 #[derive(Debug,Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Talk {
     pub title:  String,
     pub body:   String,
@@ -246,14 +431,158 @@ pub struct Talk {
 
 ///Список личных сообщений
 #[derive(Debug,Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct TalkItem {
     pub id: u32,
     pub title:  String,
     pub users:  Vec<String>,
 }
 
+///Пользователь из списка пользователей `/people/`
+#[derive(Debug,Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct UserItem {
+    pub username:   String,
+    pub realname:   String,
+
+    ///Силушка
+    pub skill:      f32,
+
+    ///Кармочка
+    pub rating:     f32,
+
+    ///URL картинки, как в [профиле](struct.UserInfo.html)
+    pub userpic:    String,
+}
+
+///Блог из списка блогов `/blogs/`
+#[derive(Debug,Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct BlogItem {
+    ///Короткое имя блога из ссылки, например `lighthouse`
+    pub name:       String,
+    pub title:      String,
+
+    ///Кол-во читателей
+    pub readers:    u32,
+    pub rating:     f32,
+    pub avatar:     String,
+}
+
+///Одна страница списка вместе с положением в пагинаторе
+#[derive(Debug,Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Page<T> {
+    pub items:          Vec<T>,
+
+    ///Номер страницы, начиная с 1
+    pub page:           u32,
+
+    ///Всего страниц, насколько видно из пагинатора
+    pub total_pages:    u32,
+
+    ///Ссылка на следующую страницу, None на последней
+    pub next:           Option<String>,
+
+    ///Ссылка на предыдущую страницу, None на первой
+    pub prev:           Option<String>,
+
+    ///Общее кол-во элементов на всех страницах, если Табун его показал
+    ///(например, в поиске)
+    pub total:          Option<u32>,
+}
+
+///Запись на стене пользователя
+#[derive(Debug,Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct WallEntry {
+    pub id:             u32,
+    pub author:         String,
+    pub date:           String,
+    pub body:           String,
+
+    ///Ответы на запись. На странице стены есть только последние из них,
+    ///остальные догружаются через
+    ///[`get_wall_replies`](struct.TClient.html#method.get_wall_replies)
+    pub replies:        Vec<WallEntry>,
+
+    ///Общее кол-во ответов
+    pub replies_count:  u32,
+}
+
+///Тэг из облака тэгов избранного
+#[derive(Debug,Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct FavouriteTag {
+    pub name:   String,
+
+    ///Сколько раз пользователь использовал тэг
+    pub count:  u32,
+}
+
+///Настройки профиля текущего пользователя со страницы `/settings/profile/`
+#[derive(Debug,Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ProfileSettings {
+    pub realname:       String,
+    pub about:          String,
+    pub gender:         Gender,
+
+    ///День, месяц и год рождения
+    pub birthday:       Option<(u32,u32,u32)>,
+
+    ///ID страны, региона и города из справочника сайта
+    ///(как в выпадающих списках на странице настроек), пустая строка —
+    ///не указано
+    pub country:        String,
+    pub region:         String,
+    pub city:           String,
+
+    ///Контакты в виде пар ID типа контакта - значение
+    pub contacts:       Vec<(String,String)>,
+}
+
+///Настройки уведомлений на почту со страницы `/settings/tuning/`
+#[derive(Debug,Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct NoticeSettings {
+    ///О новых постах в блогах, на которые подписан
+    pub new_topic:      bool,
+
+    ///О новых комментариях в постах
+    pub new_comment:    bool,
+
+    ///О новых личных сообщениях
+    pub new_talk:       bool,
+
+    ///Об ответах на комментарии
+    pub reply_comment:  bool,
+
+    ///О новых друзьях
+    pub new_friend:     bool,
+}
+
 //Реализации
 
+///Сообщения об ошибках над непринятой формой, см.
+///[`doc_get_form_errors`](struct.TClient.html#method.doc_get_form_errors)
+fn form_errors(doc: &Document) -> Vec<(String,String)> {
+    doc.find(Class("system-message-error")).find(Name("li")).iter()
+        .map(|li| {
+            let title = match li.find(Name("strong")).first() {
+                Some(x) => x.text(),
+                None    => String::new()
+            };
+            let text = li.text();
+            let text = text.trim()
+                .trim_left_matches(title.as_str())
+                .trim_left_matches(':')
+                .trim();
+            (title.trim().trim_right_matches(':').trim().to_string(), text.to_string())
+        })
+        .collect()
+}
+
 impl From<StatusCode> for TabunError {
     fn from(x: StatusCode) -> Self {
         TabunError::NumError(x)
@@ -272,6 +601,13 @@ impl From<std::io::Error> for TabunError {
     }
 }
 
+#[cfg(feature = "archive")]
+impl From<rusqlite::Error> for TabunError {
+    fn from(x: rusqlite::Error) -> Self {
+        TabunError::DatabaseError(x)
+    }
+}
+
 impl Display for Comment {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "Comment({},\"{}\",\"{}\")", self.id, self.author, self.body)
@@ -353,7 +689,7 @@ impl TClientBuilder {
         let mut user = TClient{
             name:               String::new(),
             security_ls_key:    self.security_ls_key.clone(),
-            client:             self.client,
+            client:             Arc::new(self.client),
             cookies:            CookieJar::new(format!("{:?}",std::time::SystemTime::now()).as_bytes()),
             host:               self.host,
         };
@@ -393,7 +729,7 @@ impl<'a> TClient<'a> {
         let mut user = TClient{
             name:               String::new(),
             security_ls_key:    String::new(),
-            client:             Client::new(),
+            client:             Arc::new(Client::new()),
             cookies:            CookieJar::new(format!("{:?}",std::time::SystemTime::now()).as_bytes()),
             host:               String::from(HOST_URL),
         };
@@ -557,6 +893,54 @@ impl<'a> TClient<'a> {
         Ok(res)
     }
 
+    /// Отправляет форму создания или редактирования поста, добавляя к полям
+    /// security_ls_key и кнопку публикации или сохранения в черновиках.
+    /// Возвращает ID поста из перенаправления.
+    fn submit_topic_form(&mut self, form_url: &str, fields: &[(&str, &str)], publish: bool) -> TabunResult<u32> {
+        let key = self.security_ls_key.to_owned();
+
+        let mut bd = fields.to_vec();
+        bd.push(("security_ls_key", &key));
+
+        if publish {
+            bd.push(("submit_topic_publish", "Опубликовать"));
+        } else {
+            bd.push(("submit_topic_save", "Сохранить в черновиках"));
+        }
+
+        let res = try!(self.post_multipart(form_url, &bd));
+        self.parse_form_redirect(res, r"(\d+).html$")
+    }
+
+    /// Разбирает ответ на отправленную форму. Если Табун перенаправил на
+    /// созданную страницу, то достаёт из ссылки номер первой группой `regex`,
+    /// иначе форма не принята и со страницы собираются сообщения об ошибках.
+    fn parse_form_redirect(&self, mut res: hyper::client::Response, regex: &str) -> TabunResult<u32> {
+        let location = res.headers.get_raw("location")
+            .and_then(|x| x.get(0))
+            .map(|x| String::from_utf8_lossy(x).into_owned());
+
+        if let Some(location) = location {
+            return Ok(try_to_parse!(hado!{
+                reg <- Regex::new(regex).ok();
+                captures <- reg.captures(&location);
+                at <- captures.at(1);
+                at.parse::<u32>().ok()
+            }, "Cannot parse redirect location"));
+        }
+
+        let mut data = String::new();
+        try!(res.read_to_string(&mut data));
+
+        Err(TabunError::ValidationError(self.doc_get_form_errors(&Document::from(data.as_str()))))
+    }
+
+    /// Собирает со страницы сообщения об ошибках, которые Табун показывает
+    /// над непринятой формой, в виде пар заголовок-текст.
+    pub fn doc_get_form_errors(&self, doc: &Document) -> Vec<(String,String)> {
+        form_errors(doc)
+    }
+
     /// Отправляет ajax-запрос и возвращает распарсенный json-ответ (Value).
     /// Он гарантированно является json-объектом (то есть можно использовать
     /// `.as_object().unwrap()`, если нужно)
@@ -726,6 +1110,31 @@ impl<'a> TClient<'a> {
         Ok(try_to_parse!(img.attr("src")).to_string())
     }
 
+    ///Прогоняет текст через Табун и возвращает HTML в том виде, в котором
+    ///он будет показан на сайте (после всех фильтров и чистки тэгов),
+    ///ничего при этом не публикуя. Подходит и для комментов, и для
+    ///личных сообщений
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let html = user.preview_text("<b>Жирный</b> <script>alert(1)</script>").unwrap();
+    ///```
+    pub fn preview_text(&mut self, body: &str) -> TabunResult<String> {
+        self.preview("/ajax/preview/text/", &[("text", body), ("save", "0")])
+    }
+
+    ///Запрашивает предпросмотр и возвращает отрисованный HTML
+    ///(внутренний метод для preview_text и add_post_dry_run)
+    fn preview(&mut self, url: &str, fields: &[(&str, &str)]) -> TabunResult<String> {
+        let data = try!(self.ajax(url, fields));
+
+        match get_json!(data, "/sText", as_str) {
+            Some(x) => Ok(x.trim().to_string()),
+            None => Err(parse_error!("Server did not return sText"))
+        }
+    }
+
     ///Получает ID блога по его имени
     ///
     ///# Examples
@@ -1005,12 +1414,76 @@ impl<'a> TClient<'a> {
             None => Err(parse_error!("Server did not return iCount"))
         }
     }
+
+    ///Задаёт тэги избранного, true - коммент, false - пост
+    ///(внутренний метод для публичных set_favourite_post_tags и set_favourite_comment_tags)
+    fn set_favourite_tags(&mut self, id: u32, tags: &[&str], fn_typ: bool) -> TabunResult<Vec<String>> {
+        let id = id.to_string();
+        let tags = tags.join(",");
+
+        let body = vec![
+            ("target_type", if fn_typ { "comment" } else { "topic" }),
+            ("target_id",   id.as_str()),
+            ("tags",        tags.as_str())
+        ];
+
+        let data = try!(self.ajax("/ajax/favourite/save-tags/", &body));
+
+        let tags = match get_json!(data, "/aTags", as_array) {
+            Some(x) => x,
+            None => return Err(parse_error!("Server did not return aTags"))
+        };
+
+        let mut ret = Vec::with_capacity(tags.len());
+        for tag in tags {
+            ret.push(try_to_parse!(get_json!(tag, "/tag", as_str)).to_string());
+        }
+
+        Ok(ret)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use ::{TClient};
     use ::regex::{Error,Regex};
+    use ::select::document::Document;
+
+    ///Обычный пост для тестов, нужные поля тесты меняют сами
+    #[cfg(any(feature = "serialize", feature = "archive"))]
+    pub fn post(id: u32) -> ::Post {
+        ::Post{
+            title:          "Заголовок".to_string(),
+            body:           "Текст".to_string(),
+            date:           "2016-09-01T12:00:00+03:00".to_string(),
+            tags:           vec!["тэг".to_string()],
+            favourite_tags: Vec::new(),
+            comments_count: 0,
+            author:         "Orhideous".to_string(),
+            id:             id,
+            draft:          false,
+            has_cut:        false,
+            topic_type:     ::TopicType::Topic,
+            link:           None,
+            photos:         Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_form_errors() {
+        let doc = Document::from(concat!(
+            r#"<div class="system-message-error"><ul class="system-message-list">"#,
+            r#"<li><strong>Заголовок:</strong> Название топика должно быть от 2 до 200 символов</li>"#,
+            r#"<li>Нужно выбрать блог</li>"#,
+            r#"</ul></div><ul><li>Не ошибка</li></ul>"#
+        ));
+
+        assert_eq!(::form_errors(&doc), vec![
+            ("Заголовок".to_string(), "Название топика должно быть от 2 до 200 символов".to_string()),
+            (String::new(), "Нужно выбрать блог".to_string()),
+        ]);
+        assert!(::form_errors(&Document::from("<div></div>")).is_empty());
+    }
 
     #[test]
     fn test_parsetext_macro() {
@@ -1030,6 +1503,26 @@ mod test {
         }
     }
 
+    #[cfg(feature = "serialize")]
+    #[test]
+    fn test_post_json() {
+        use ::{Post, TopicType, Photo};
+
+        let mut post = post(157198);
+        post.comments_count = 3;
+        post.topic_type = TopicType::Photoset;
+        post.photos = vec![Photo{ url: "//cdn.everypony.ru/1.png".to_string(), description: String::new() }];
+
+        let json = ::serde_json::to_string(&post).unwrap();
+        assert!(json.contains("\"topic_type\":\"photoset\""));
+        assert!(json.contains("\"link\":null"));
+
+        let back: Post = ::serde_json::from_str(&json).unwrap();
+        assert_eq!(back.id, post.id);
+        assert_eq!(back.topic_type, TopicType::Photoset);
+        assert_eq!(back.photos[0].url, post.photos[0].url);
+    }
+
     #[test]
     fn test_get_profile() {
         let mut user = TClient::new(None,None).unwrap();
@@ -0,0 +1,250 @@
+/* Lazy paginated listings
+ *
+ * Copyright (C) 2016 TyanNN <TyanNN@cocaine.ninja>
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along
+ * with this program; if not, write to the Free Software Foundation, Inc.,
+ * 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+*/
+
+extern crate select;
+extern crate regex;
+
+use super::*;
+
+use select::document::Document;
+use select::predicate::{Class, Name, And};
+
+use regex::Regex;
+
+use std::cmp::max;
+use std::collections::VecDeque;
+
+///Ссылка на соседнюю страницу из пагинатора: `js_class` - класс ссылки,
+///`li_class` - класс пункта пагинатора, если ссылка без класса
+fn page_link(doc: &Document, js_class: &str, li_class: &str) -> Option<String> {
+    doc.find(Class(js_class)).first()
+        .or_else(|| doc.find(Class("pagination")).find(And(Name("li"),Class(li_class))).find(Name("a")).first())
+        .and_then(|x| x.attr("href"))
+        .map(|x| x.to_string())
+}
+
+///Разбирает пагинатор. Если его нет, то страница одна
+fn parse_page<T>(doc: &Document, items: Vec<T>) -> Page<T> {
+    let paginator = doc.find(Class("pagination"));
+
+    let page = paginator.find(And(Name("li"),Class("active"))).first()
+        .and_then(|x| x.text().trim().parse::<u32>().ok())
+        .unwrap_or(1);
+
+    // Последняя страница - наибольший номер среди ссылок пагинатора
+    let re = Regex::new(r"/page(\d+)/").unwrap();
+    let total_pages = paginator.find(Name("a")).iter()
+        .filter_map(|a| a.attr("href"))
+        .filter_map(|href| re.captures(href).and_then(|c| c.at(1).and_then(|x| x.parse::<u32>().ok())))
+        .fold(page, max);
+
+    Page{
+        items:          items,
+        page:           page,
+        total_pages:    total_pages,
+        next:           page_link(doc, "js-paging-next-page", "next"),
+        prev:           page_link(doc, "js-paging-prev-page", "prev"),
+        total:          None,
+    }
+}
+
+impl<'a> TClient<'a> {
+    ///Заворачивает элементы со страницы в [`Page`](struct.Page.html),
+    ///достав номер страницы и ссылки на соседние из пагинатора
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let doc = user.get_document("/profile/Orhideous/created/topics/page2/").unwrap();
+    ///let posts = user.doc_get_posts(&doc).unwrap();
+    ///let page = user.doc_get_page(&doc, posts);
+    ///println!("{} из {}", page.page, page.total_pages);
+    ///```
+    pub fn doc_get_page<T>(&self, doc: &Document, items: Vec<T>) -> Page<T> {
+        parse_page(doc, items)
+    }
+}
+
+///Список, разбитый на страницы, например посты блога или личные сообщения.
+///Страницы загружаются по мере надобности, когда до них доходит итератор
+///
+///# Examples
+///```no_run
+///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+///for post in user.posts("fanart").start(3).limit(50).iter() {
+///    let post = post.unwrap();
+///    println!("{} - {}", post.id, post.title);
+///}
+///```
+pub struct Listing<'c, 'a: 'c, T> {
+    client:     &'c mut TClient<'a>,
+    url:        Box<Fn(u32) -> String>,
+    parse:      fn(&mut TClient<'a>, &Document) -> TabunResult<Vec<T>>,
+    start:      u32,
+    max_pages:  Option<u32>,
+    limit:      Option<usize>,
+}
+
+impl<'c, 'a: 'c, T> Listing<'c, 'a, T> {
+    ///Создаёт список. `url` по номеру страницы возвращает ссылку на неё,
+    ///`parse` достаёт элементы со страницы
+    pub fn new(client: &'c mut TClient<'a>, url: Box<Fn(u32) -> String>, parse: fn(&mut TClient<'a>, &Document) -> TabunResult<Vec<T>>) -> Listing<'c, 'a, T> {
+        Listing{
+            client:     client,
+            url:        url,
+            parse:      parse,
+            start:      1,
+            max_pages:  None,
+            limit:      None,
+        }
+    }
+
+    ///Номер страницы, с которой начинать
+    pub fn start(mut self, page: u32) -> Listing<'c, 'a, T> {
+        self.start = page;
+        self
+    }
+
+    ///Сколько страниц загрузить, не больше
+    pub fn pages(mut self, count: u32) -> Listing<'c, 'a, T> {
+        self.max_pages = Some(count);
+        self
+    }
+
+    ///Сколько элементов выдать, не больше
+    pub fn limit(mut self, count: usize) -> Listing<'c, 'a, T> {
+        self.limit = Some(count);
+        self
+    }
+
+    pub fn iter(self) -> ListingIter<'c, 'a, T> {
+        self.into_iter()
+    }
+}
+
+impl<'c, 'a: 'c, T> IntoIterator for Listing<'c, 'a, T> {
+    type Item = TabunResult<T>;
+    type IntoIter = ListingIter<'c, 'a, T>;
+
+    fn into_iter(self) -> ListingIter<'c, 'a, T> {
+        ListingIter{
+            page:       self.start,
+            pages_left: self.max_pages,
+            left:       self.limit,
+            buffer:     VecDeque::new(),
+            done:       false,
+            listing:    self,
+        }
+    }
+}
+
+///Итератор по списку, выдаёт элементы страница за страницей и
+///останавливается на последней странице или после первой ошибки
+pub struct ListingIter<'c, 'a: 'c, T> {
+    listing:    Listing<'c, 'a, T>,
+    page:       u32,
+    pages_left: Option<u32>,
+    left:       Option<usize>,
+    buffer:     VecDeque<T>,
+    done:       bool,
+}
+
+impl<'c, 'a: 'c, T> ListingIter<'c, 'a, T> {
+    ///Номер страницы, которая будет загружена следующей
+    pub fn page(&self) -> u32 {
+        self.page
+    }
+
+    fn fetch(&mut self) -> TabunResult<()> {
+        let url = (self.listing.url)(self.page);
+        let doc = try!(self.listing.client.get_document(&url));
+        let items = try!((self.listing.parse)(&mut *self.listing.client, &doc));
+        let page = parse_page(&doc, items);
+
+        if page.items.is_empty() || page.next.is_none() {
+            self.done = true;
+        }
+
+        self.page += 1;
+        if let Some(ref mut x) = self.pages_left {
+            *x -= 1;
+        }
+
+        self.buffer.extend(page.items);
+        Ok(())
+    }
+}
+
+impl<'c, 'a: 'c, T> Iterator for ListingIter<'c, 'a, T> {
+    type Item = TabunResult<T>;
+
+    fn next(&mut self) -> Option<TabunResult<T>> {
+        if self.left == Some(0) {
+            return None;
+        }
+
+        while self.buffer.is_empty() {
+            if self.done || self.pages_left == Some(0) {
+                return None;
+            }
+
+            if let Err(x) = self.fetch() {
+                self.done = true;
+                return Some(Err(x));
+            }
+        }
+
+        if let Some(ref mut x) = self.left {
+            *x -= 1;
+        }
+        self.buffer.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_page;
+    use select::document::Document;
+
+    #[test]
+    fn test_parse_page() {
+        let middle = Document::from(concat!(
+            r#"<div class="pagination"><ul><li class="prev"><a href="/blog/fanart/page1/" class="js-paging-prev-page">&larr;</a></li>"#,
+            r#"<li class="active">2</li><li><a href="/blog/fanart/page3/">3</a></li>"#,
+            r#"<li class="next"><a href="/blog/fanart/page3/" class="js-paging-next-page">&rarr;</a></li></ul></div>"#
+        ));
+        let last = Document::from(concat!(
+            r#"<div class="pagination"><ul><li class="prev"><a href="/blog/fanart/page2/" class="js-paging-prev-page">&larr;</a></li>"#,
+            r#"<li class="active">3</li><li class="next">&rarr;</li></ul></div>"#
+        ));
+
+        let page = parse_page(&middle, vec![1, 2]);
+        assert_eq!(page.items, vec![1, 2]);
+        assert_eq!((page.page, page.total_pages), (2, 3));
+        assert_eq!(page.next, Some("/blog/fanart/page3/".to_string()));
+        assert_eq!(page.prev, Some("/blog/fanart/page1/".to_string()));
+
+        let page = parse_page(&last, Vec::<u32>::new());
+        assert_eq!((page.page, page.total_pages), (3, 3));
+        assert_eq!(page.next, None);
+
+        let page = parse_page(&Document::from("<div></div>"), Vec::<u32>::new());
+        assert_eq!((page.page, page.total_pages, page.next, page.prev), (1, 1, None, None));
+    }
+}
@@ -0,0 +1,617 @@
+/* Markdown to Tabun markup converter
+ *
+ * Copyright (C) 2016 TyanNN <TyanNN@cocaine.ninja>
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along
+ * with this program; if not, write to the Free Software Foundation, Inc.,
+ * 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+*/
+
+//! Перевод Markdown в HTML, который принимает Табун.
+//!
+//! Помимо обычного Markdown (заголовки, списки, цитаты, код, ссылки,
+//! картинки, выделение) понимает:
+//!
+//! * `@логин` — упоминание пользователя, `<ls user="логин" />`
+//! * `[cut]` или `[cut Читать дальше]` на отдельной строке — кат
+//! * спойлеры:
+//!
+//! ```text
+//! :::spoiler Заголовок
+//! Текст спойлера
+//! :::
+//! ```
+//!
+//! Табун сам превращает переносы строк в `<br/>`, поэтому абзацы
+//! разделяются пустыми строками, а не `<p>`. Заголовки переводятся
+//! в `h4`-`h6`, так как старшие Табун вырезает.
+//!
+//! ```
+//! let res = libtabun::markdown::to_tabun("Привет, **@Orhideous**!");
+//! assert_eq!(res.html, r#"Привет, <strong><ls user="Orhideous" /></strong>!"#);
+//! assert!(res.warnings.is_empty());
+//! ```
+
+extern crate regex;
+
+use std::fmt;
+use std::cmp::{min, max};
+
+use regex::Regex;
+
+use markup::escape;
+
+///Тэги, которые пропускает Табун, остальные он вырезает
+const ALLOWED_TAGS: &'static [&'static str] = &[
+    "a", "img", "i", "b", "u", "s", "small", "video", "em", "strong", "nobr",
+    "li", "ol", "ul", "sup", "abbr", "sub", "acronym", "h4", "h5", "h6", "br",
+    "hr", "pre", "code", "object", "param", "embed", "blockquote", "iframe",
+    "table", "th", "tr", "td", "cut", "ls", "span"
+];
+
+///Что-то в тексте, что Табун не покажет так, как задумано
+#[derive(Debug,Clone,PartialEq)]
+pub enum MarkdownWarning {
+    ///HTML-тэг, который Табун вырежет
+    ForbiddenTag(String),
+
+    ///Ссылка на `javascript:`, вместо неё оставлен только текст
+    UnsafeLink(String),
+
+    ///Спойлер с указанным заголовком не закрыт, закрыт в конце текста
+    UnclosedSpoiler(String),
+
+    ///Блок кода не закрыт, закрыт в конце текста
+    UnclosedCode,
+}
+
+impl fmt::Display for MarkdownWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MarkdownWarning::ForbiddenTag(ref x) =>
+                write!(f, "Табун вырежет тэг <{}>", x),
+            MarkdownWarning::UnsafeLink(ref x) =>
+                write!(f, "Ссылка \"{}\" убрана, Табун такие не пропускает", x),
+            MarkdownWarning::UnclosedSpoiler(ref x) =>
+                write!(f, "Спойлер \"{}\" не закрыт", x),
+            MarkdownWarning::UnclosedCode =>
+                write!(f, "Блок кода не закрыт"),
+        }
+    }
+}
+
+///Результат перевода
+#[derive(Debug,Clone)]
+pub struct Converted {
+    ///Готовый текст для `add_post` или `comment`
+    pub html:       String,
+    pub warnings:   Vec<MarkdownWarning>,
+}
+
+///Переводит Markdown в разметку Табуна
+pub fn to_tabun(src: &str) -> Converted {
+    let lines = src.replace('\t', "    ").lines().map(|x| x.to_string()).collect::<Vec<_>>();
+
+    let mut conv = Converter{ warnings: Vec::new() };
+    let html = conv.blocks(&lines);
+
+    Converted{
+        html:       html,
+        warnings:   conv.warnings
+    }
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_left_matches(' ').len()
+}
+
+///Убирает до `n` пробелов в начале строки
+fn dedent(line: &str, n: usize) -> String {
+    line[min(indent(line), n)..].to_string()
+}
+
+fn is_hr(line: &str) -> bool {
+    let chars = line.chars().filter(|x| *x != ' ').collect::<Vec<_>>();
+    chars.len() >= 3 && indent(line) < 4 && ['-','*','_'].iter().any(|c| chars.iter().all(|x| x == c))
+}
+
+///Уровень заголовка и его текст
+fn heading(line: &str) -> Option<(usize, String)> {
+    if indent(line) >= 4 {
+        return None;
+    }
+
+    let line = line.trim();
+    let level = line.chars().take_while(|x| *x == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+
+    Some((level, rest.trim().trim_right_matches('#').trim().to_string()))
+}
+
+///Нумерованный ли список и с какого отступа начинается текст пункта
+fn list_marker(line: &str) -> Option<(bool, usize)> {
+    let ind = indent(line);
+    if ind >= 4 {
+        return None;
+    }
+
+    let rest = &line[ind..];
+    let digits = rest.chars().take_while(|x| x.is_digit(10)).count();
+
+    let (ordered, marker_len) = if digits > 0 && digits < 10 && (rest[digits..].starts_with(". ") || rest[digits..].starts_with(") ")) {
+        (true, digits + 1)
+    } else if rest.starts_with("- ") || rest.starts_with("* ") || rest.starts_with("+ ") {
+        (false, 1)
+    } else {
+        return None;
+    };
+
+    let spaces = indent(&rest[marker_len..]);
+    let spaces = if spaces > 4 { 1 } else { spaces };
+    Some((ordered, ind + marker_len + spaces))
+}
+
+fn is_fence(line: &str) -> bool {
+    indent(line) < 4 && (line.trim().starts_with("```") || line.trim().starts_with("~~~"))
+}
+
+fn is_quote(line: &str) -> bool {
+    indent(line) < 4 && line.trim_left().starts_with('>')
+}
+
+fn is_spoiler_start(line: &str) -> bool {
+    line.trim().starts_with(":::spoiler")
+}
+
+fn is_cut(line: &str) -> bool {
+    let line = line.trim();
+    line == "[cut]" || (line.starts_with("[cut ") && line.ends_with(']'))
+}
+
+fn is_block_start(line: &str) -> bool {
+    heading(line).is_some() || is_hr(line) || is_fence(line) || is_quote(line) ||
+        list_marker(line).is_some() || is_spoiler_start(line) || is_cut(line)
+}
+
+struct Converter {
+    warnings: Vec<MarkdownWarning>,
+}
+
+impl Converter {
+    fn warn(&mut self, warning: MarkdownWarning) {
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
+    ///Переводит блоки. Абзацы разделяются пустой строкой, а остальные
+    ///блоки просто переносом, чтобы Табун не наставил между ними лишних `<br/>`
+    fn blocks(&mut self, lines: &[String]) -> String {
+        let mut ret = String::new();
+        let mut prev_paragraph = false;
+        let mut i = 0;
+
+        while i < lines.len() {
+            let line = lines[i].as_str();
+
+            if line.trim().is_empty() {
+                i += 1;
+                continue;
+            }
+
+            let mut paragraph = false;
+
+            let block = if is_fence(line) {
+                let fence = &line.trim()[..3];
+                let mut code = Vec::new();
+                i += 1;
+
+                while i < lines.len() && !(indent(&lines[i]) < 4 && lines[i].trim() == fence) {
+                    code.push(lines[i].as_str());
+                    i += 1;
+                }
+
+                if i >= lines.len() {
+                    self.warn(MarkdownWarning::UnclosedCode);
+                }
+                i += 1;
+
+                format!("<pre><code>{}</code></pre>", escape(&code.join("\n")))
+            } else if is_spoiler_start(line) {
+                let title = line.trim()[":::spoiler".len()..].trim().to_string();
+                let mut body = Vec::new();
+                let mut depth = 1;
+                i += 1;
+
+                while i < lines.len() {
+                    if is_spoiler_start(&lines[i]) {
+                        depth += 1;
+                    } else if lines[i].trim() == ":::" {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    body.push(lines[i].clone());
+                    i += 1;
+                }
+
+                if i >= lines.len() {
+                    self.warn(MarkdownWarning::UnclosedSpoiler(title.clone()));
+                }
+                i += 1;
+
+                format!(
+                    "<span class=\"spoiler\"><span class=\"spoiler-title\">{}</span><span class=\"spoiler-body\">{}</span></span>",
+                    self.inline(&title),
+                    self.blocks(&body)
+                )
+            } else if is_cut(line) {
+                i += 1;
+
+                let name = line.trim()[4..line.trim().len() - 1].trim();
+                if name.is_empty() {
+                    "<cut>".to_string()
+                } else {
+                    format!("<cut name=\"{}\">", escape(name))
+                }
+            } else if let Some((level, text)) = heading(line) {
+                i += 1;
+
+                let tag = match level {
+                    1 => "h4",
+                    2 => "h5",
+                    _ => "h6"
+                };
+                format!("<{}>{}</{}>", tag, self.inline(&text), tag)
+            } else if is_hr(line) {
+                i += 1;
+                "<hr/>".to_string()
+            } else if is_quote(line) {
+                let mut body = Vec::new();
+
+                while i < lines.len() && is_quote(&lines[i]) {
+                    let rest = &lines[i].trim_left()[1..];
+                    body.push(if rest.starts_with(' ') { rest[1..].to_string() } else { rest.to_string() });
+                    i += 1;
+                }
+
+                format!("<blockquote>{}</blockquote>", self.blocks(&body))
+            } else if let Some((ordered, _)) = list_marker(line) {
+                let mut items: Vec<Vec<String>> = Vec::new();
+                let mut offset = 0;
+
+                while i < lines.len() {
+                    let cur = lines[i].as_str();
+
+                    match list_marker(cur) {
+                        Some((o, off)) if o == ordered && indent(cur) < max(offset, 1) || items.is_empty() => {
+                            items.push(vec![cur[off..].to_string()]);
+                            offset = off;
+                        },
+                        _ if cur.trim().is_empty() => {
+                            // Пустая строка продолжает список, только если за ней идёт
+                            // вложенный текст или следующий пункт
+                            let next = lines.get(i + 1).map(|x| x.as_str()).unwrap_or("");
+                            let continues = indent(next) >= offset ||
+                                list_marker(next).map_or(false, |(o, _)| o == ordered && indent(next) < offset);
+                            if !continues || next.trim().is_empty() {
+                                break;
+                            }
+                            items.last_mut().unwrap().push(String::new());
+                        },
+                        _ if indent(cur) >= offset => {
+                            items.last_mut().unwrap().push(dedent(cur, offset));
+                        },
+                        _ if !is_block_start(cur) && !items.last().unwrap().last().unwrap().trim().is_empty() => {
+                            // Ленивое продолжение абзаца внутри пункта
+                            items.last_mut().unwrap().push(cur.trim().to_string());
+                        },
+                        _ => break
+                    }
+                    i += 1;
+                }
+
+                let tag = if ordered { "ol" } else { "ul" };
+                let items = items.iter()
+                    .map(|x| format!("<li>{}</li>", self.blocks(x)))
+                    .collect::<Vec<_>>();
+                format!("<{}>{}</{}>", tag, items.concat(), tag)
+            } else {
+                let mut text = String::new();
+
+                while i < lines.len() && !lines[i].trim().is_empty() && (text.is_empty() || !is_block_start(&lines[i])) {
+                    let cur = lines[i].trim_left();
+                    if !text.is_empty() && !text.ends_with('\n') {
+                        text.push(' ');
+                    }
+
+                    // Два пробела или обратный слэш в конце - жёсткий перенос,
+                    // а Табун и так превращает переносы в <br/>
+                    if cur.ends_with("  ") {
+                        text.push_str(cur.trim_right());
+                        text.push('\n');
+                    } else if cur.ends_with('\\') {
+                        text.push_str(&cur[..cur.len() - 1]);
+                        text.push('\n');
+                    } else {
+                        text.push_str(cur);
+                    }
+                    i += 1;
+                }
+
+                paragraph = true;
+                self.inline(text.trim_right())
+            };
+
+            if !ret.is_empty() {
+                ret.push_str(if paragraph && prev_paragraph { "\n\n" } else { "\n" });
+            }
+            ret.push_str(&block);
+            prev_paragraph = paragraph;
+        }
+
+        ret
+    }
+
+    ///Переводит выделение, ссылки, картинки, код и упоминания внутри абзаца
+    fn inline(&mut self, text: &str) -> String {
+        let tag_re = Regex::new(r"^</?([A-Za-z][A-Za-z0-9]*)(\s[^<>]*)?/?>").unwrap();
+        let autolink_re = Regex::new(r"^<((https?|ftp)://[^<>\s]+)>").unwrap();
+        let entity_re = Regex::new(r"^&(#[0-9]+|#[xX][0-9a-fA-F]+|[A-Za-z][A-Za-z0-9]*);").unwrap();
+
+        let mut ret = String::new();
+        let mut i = 0;
+
+        while i < text.len() {
+            let rest = &text[i..];
+            let c = rest.chars().next().unwrap();
+            let prev = text[..i].chars().next_back();
+
+            match c {
+                '\\' if rest[1..].chars().next().map_or(false, |x| "\\`*_{}[]()#+-.!~<>@|".contains(x)) => {
+                    let x = rest[1..].chars().next().unwrap();
+                    ret.push_str(&escape(&x.to_string()));
+                    i += 1 + x.len_utf8();
+                    continue;
+                },
+                '`' => {
+                    let ticks = rest.chars().take_while(|x| *x == '`').count();
+                    let fence = &rest[..ticks];
+                    if let Some(end) = rest[ticks..].find(fence) {
+                        let code = rest[ticks..ticks + end].trim();
+                        ret.push_str(&format!("<code>{}</code>", escape(code)));
+                        i += ticks + end + ticks;
+                    } else {
+                        ret.push_str(fence);
+                        i += ticks;
+                    }
+                    continue;
+                },
+                '*' | '_' | '~' => {
+                    // Подчёркивания внутри слов (snake_case) выделением не считаются
+                    let in_word = c == '_' && prev.map_or(false, |x| x.is_alphanumeric());
+
+                    let (delim, tag) = if rest.starts_with("**") || rest.starts_with("__") {
+                        (&rest[..2], "strong")
+                    } else if rest.starts_with("~~") {
+                        (&rest[..2], "s")
+                    } else if c != '~' {
+                        (&rest[..1], "em")
+                    } else {
+                        (&rest[..1], "")
+                    };
+
+                    let end = if in_word || tag.is_empty() {
+                        None
+                    } else {
+                        rest[delim.len()..].find(delim).and_then(|x| {
+                            let inner = &rest[delim.len()..delim.len() + x];
+                            if !inner.is_empty() && !inner.starts_with(' ') && !inner.ends_with(' ') {
+                                Some(delim.len() + x)
+                            } else {
+                                None
+                            }
+                        })
+                    };
+
+                    match end {
+                        Some(end) => {
+                            let inner = self.inline(&rest[delim.len()..end]);
+                            ret.push_str(&format!("<{}>{}</{}>", tag, inner, tag));
+                            i += end + delim.len();
+                        },
+                        None => {
+                            ret.push_str(delim);
+                            i += delim.len();
+                        }
+                    }
+                    continue;
+                },
+                '!' if rest.starts_with("![") => {
+                    if let Some((alt, url, len)) = link_parts(&rest[1..]) {
+                        ret.push_str(&format!("<img src=\"{}\" alt=\"{}\" />", escape(&url), escape(&alt)));
+                        i += 1 + len;
+                        continue;
+                    }
+                },
+                '[' => {
+                    if let Some((label, url, len)) = link_parts(rest) {
+                        let label = self.inline(&label);
+                        if url.trim().to_lowercase().starts_with("javascript:") {
+                            self.warn(MarkdownWarning::UnsafeLink(url));
+                            ret.push_str(&label);
+                        } else {
+                            ret.push_str(&format!("<a href=\"{}\">{}</a>", escape(&url), label));
+                        }
+                        i += len;
+                        continue;
+                    }
+                },
+                '<' => {
+                    if let Some(caps) = autolink_re.captures(rest) {
+                        let url = caps.at(1).unwrap_or("");
+                        ret.push_str(&format!("<a href=\"{0}\">{0}</a>", escape(url)));
+                        i += caps.at(0).unwrap_or("").len();
+                        continue;
+                    }
+
+                    if let Some(caps) = tag_re.captures(rest) {
+                        let name = caps.at(1).unwrap_or("").to_lowercase();
+                        if !ALLOWED_TAGS.contains(&name.as_str()) {
+                            self.warn(MarkdownWarning::ForbiddenTag(name));
+                        }
+
+                        // Разметку HTML оставляем как есть
+                        let tag = caps.at(0).unwrap_or("");
+                        ret.push_str(tag);
+                        i += tag.len();
+                        continue;
+                    }
+                },
+                '&' => {
+                    if let Some(caps) = entity_re.captures(rest) {
+                        let entity = caps.at(0).unwrap_or("");
+                        ret.push_str(entity);
+                        i += entity.len();
+                        continue;
+                    }
+                },
+                '@' if !prev.map_or(false, |x| x.is_alphanumeric() || x == '_') => {
+                    let login = rest[1..].chars()
+                        .take_while(|x| x.is_digit(36) || *x == '_' || *x == '-')
+                        .collect::<String>();
+                    let login = login.trim_right_matches('-');
+
+                    if !login.is_empty() {
+                        ret.push_str(&format!("<ls user=\"{}\" />", login));
+                        i += 1 + login.len();
+                        continue;
+                    }
+                },
+                _ => {}
+            }
+
+            ret.push_str(&escape(&c.to_string()));
+            i += c.len_utf8();
+        }
+
+        ret
+    }
+}
+
+///Разбирает `[текст](ссылка "заголовок")` в начале строки, возвращает
+///текст, ссылку и длину всей конструкции в байтах
+fn link_parts(text: &str) -> Option<(String, String, usize)> {
+    let mut depth = 0;
+    let mut label_end = None;
+
+    for (pos, c) in text.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    label_end = Some(pos);
+                    break;
+                }
+            },
+            _ => {}
+        }
+    }
+
+    let label_end = match label_end {
+        Some(x) => x,
+        None    => return None
+    };
+
+    if !text[label_end + 1..].starts_with('(') {
+        return None;
+    }
+
+    let url_start = label_end + 2;
+    let url_end = match text[url_start..].find(')') {
+        Some(x) => url_start + x,
+        None    => return None
+    };
+
+    let url = text[url_start..url_end].trim();
+    let url = url.split_whitespace().next().unwrap_or("");
+    let url = url.trim_left_matches('<').trim_right_matches('>');
+
+    Some((text[1..label_end].to_string(), url.to_string(), url_end + 1))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn html(src: &str) -> String {
+        to_tabun(src).html
+    }
+
+    #[test]
+    fn test_paragraphs() {
+        assert_eq!(html("раз\nдва\n\nтри  \nчетыре"), "раз два\n\nтри\nчетыре");
+        assert_eq!(html("# Заголовок #\n\nтекст"), "<h4>Заголовок</h4>\nтекст");
+        assert_eq!(html("a < b & c &amp; d"), "a &lt; b &amp; c &amp; d");
+    }
+
+    #[test]
+    fn test_inline() {
+        assert_eq!(html("**жирный** *курсив* ~~зачёркнутый~~ `<код>`"),
+                   "<strong>жирный</strong> <em>курсив</em> <s>зачёркнутый</s> <code>&lt;код&gt;</code>");
+        assert_eq!(html("snake_case_name и 2 * 3 * 4"), "snake_case_name и 2 * 3 * 4");
+        assert_eq!(html(r"\*не курсив\*"), "*не курсив*");
+        assert_eq!(html("[Табун](https://tabun.everypony.ru \"сайт\") ![пони](//cdn.everypony.ru/1.png)"),
+                   r#"<a href="https://tabun.everypony.ru">Табун</a> <img src="//cdn.everypony.ru/1.png" alt="пони" />"#);
+    }
+
+    #[test]
+    fn test_mentions() {
+        assert_eq!(html("@Orhideous, привет"), r#"<ls user="Orhideous" />, привет"#);
+        assert_eq!(html("mail@example.com"), "mail@example.com");
+    }
+
+    #[test]
+    fn test_blocks() {
+        assert_eq!(html("- раз\n- два\n  - вложенный\n\n1. первый"),
+                   "<ul><li>раз</li><li>два\n<ul><li>вложенный</li></ul></li></ul>\n<ol><li>первый</li></ol>");
+        assert_eq!(html("> цитата\n> дальше\n\n---"), "<blockquote>цитата дальше</blockquote>\n<hr/>");
+        assert_eq!(html("```rust\nfn main() { a < b }\n```"), "<pre><code>fn main() { a &lt; b }</code></pre>");
+    }
+
+    #[test]
+    fn test_extensions() {
+        assert_eq!(html("вступление\n\n[cut Дальше]\n\nтекст"), "вступление\n<cut name=\"Дальше\">\nтекст");
+        assert_eq!(html(":::spoiler Тайна\n**текст**\n:::"),
+                   r#"<span class="spoiler"><span class="spoiler-title">Тайна</span><span class="spoiler-body"><strong>текст</strong></span></span>"#);
+    }
+
+    #[test]
+    fn test_warnings() {
+        let res = to_tabun("<div>блок</div> [клик](javascript:void) <b>можно</b>\n\n:::spoiler Без конца");
+        assert_eq!(res.warnings, vec![
+            MarkdownWarning::ForbiddenTag("div".to_string()),
+            MarkdownWarning::UnsafeLink("javascript:void".to_string()),
+            MarkdownWarning::UnclosedSpoiler("Без конца".to_string())
+        ]);
+    }
+}
@@ -0,0 +1,388 @@
+/* Tabun markup tree
+ *
+ * Copyright (C) 2016 TyanNN <TyanNN@cocaine.ninja>
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along
+ * with this program; if not, write to the Free Software Foundation, Inc.,
+ * 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+*/
+
+//! Разбор текстов постов и комментов в дерево.
+//!
+//! Понимает как разметку в том виде, в котором её пишут авторы
+//! (`<cut>`, `<ls user="...">`, `<video>`), так и то, во что её
+//! превращает Табун при показе (`Post.body`, `Comment.body`).
+//!
+//! ```
+//! use libtabun::markup::{self, MarkupNode};
+//!
+//! let tree = markup::parse(r#"Привет, <ls user="Orhideous" />!"#);
+//! assert_eq!(tree[1], MarkupNode::UserMention("Orhideous".to_string()));
+//! ```
+
+extern crate select;
+
+use select::document::Document;
+use select::node::{Node, Data};
+use select::predicate::Name;
+
+///Элементы, у которых не бывает закрывающего тэга
+const VOID_ELEMENTS: &'static [&'static str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source", "track", "wbr"
+];
+
+///Узел дерева разметки
+#[derive(Debug,Clone,PartialEq)]
+pub enum MarkupNode {
+    ///Текст, HTML-сущности уже раскрыты
+    Text(String),
+
+    ///Перенос строки, `<br>`
+    LineBreak,
+
+    ///Кат, в скобках текст ссылки "читать дальше", если автор его указал
+    Cut(Option<String>),
+
+    ///Спойлер с заголовком
+    Spoiler{
+        title:  String,
+        body:   Vec<MarkupNode>
+    },
+
+    ///Упоминание пользователя, в скобках логин
+    UserMention(String),
+
+    ///Встроенное видео, в скобках ссылка на видео или на плеер
+    Video(String),
+
+    Image{
+        src:    String,
+        alt:    String,
+        title:  String
+    },
+
+    Link{
+        href:       String,
+        children:   Vec<MarkupNode>
+    },
+
+    ///Любой другой HTML-элемент
+    Element{
+        name:       String,
+        attrs:      Vec<(String,String)>,
+        children:   Vec<MarkupNode>
+    }
+}
+
+impl MarkupNode {
+    ///Вложенные узлы, у узлов без содержимого пусто
+    pub fn children(&self) -> &[MarkupNode] {
+        match *self {
+            MarkupNode::Spoiler{ref body, ..}       => body,
+            MarkupNode::Link{ref children, ..}      => children,
+            MarkupNode::Element{ref children, ..}   => children,
+            _                                       => &[]
+        }
+    }
+}
+
+///Разбирает текст поста или коммента в дерево
+pub fn parse(body: &str) -> Vec<MarkupNode> {
+    let doc = Document::from(body);
+
+    let mut ret = Vec::new();
+    if let Some(root) = doc.find(Name("body")).first() {
+        parse_children(&root, &mut ret);
+    }
+    ret
+}
+
+///Собирает дерево обратно в разметку Табуна (в том виде, в котором её пишут
+///авторы, то есть её можно отправлять в `add_post` и `comment`)
+pub fn to_html(nodes: &[MarkupNode]) -> String {
+    let mut ret = String::new();
+    for node in nodes {
+        write_node(node, &mut ret);
+    }
+    ret
+}
+
+///Обходит дерево в глубину, вызывая `f` для каждого узла
+pub fn walk<F: FnMut(&MarkupNode)>(nodes: &[MarkupNode], f: &mut F) {
+    for node in nodes {
+        f(node);
+        walk(node.children(), f);
+    }
+}
+
+///Является ли ссылка адресом встраиваемого плеера YouTube или Vimeo
+pub fn is_video_player(src: &str) -> bool {
+    src.contains("youtube.com/embed/") ||
+        src.contains("youtube-nocookie.com/embed/") ||
+        src.contains("player.vimeo.com/video/")
+}
+
+fn has_class(node: &Node, class: &str) -> bool {
+    node.attr("class").map_or(false, |x| x.split_whitespace().any(|c| c == class))
+}
+
+///Добавляет текст, склеивая его с предыдущим текстовым узлом
+fn push_text(out: &mut Vec<MarkupNode>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+
+    if let Some(&mut MarkupNode::Text(ref mut prev)) = out.last_mut() {
+        prev.push_str(text);
+        return;
+    }
+
+    out.push(MarkupNode::Text(text.to_string()));
+}
+
+fn parse_children(node: &Node, out: &mut Vec<MarkupNode>) {
+    for child in node.children() {
+        parse_node(&child, out);
+    }
+}
+
+fn parse_node(node: &Node, out: &mut Vec<MarkupNode>) {
+    if let Some(text) = node.as_text() {
+        push_text(out, text);
+        return;
+    }
+
+    // Комментарии и прочее не-элементы пропускаем
+    let name = match node.name() {
+        Some(x) => x.to_lowercase(),
+        None    => return
+    };
+
+    match name.as_str() {
+        "br" => out.push(MarkupNode::LineBreak),
+
+        // <cut> и <ls /> не закрываются, поэтому HTML-парсер складывает в них
+        // весь следующий текст, его возвращаем на место после самого тэга
+        "cut" => {
+            out.push(MarkupNode::Cut(node.attr("name").map(|x| x.to_string())));
+            parse_children(node, out);
+        },
+        "ls" => {
+            if let Some(user) = node.attr("user") {
+                out.push(MarkupNode::UserMention(user.to_string()));
+            }
+            parse_children(node, out);
+        },
+
+        "video" => out.push(MarkupNode::Video(node.text().trim().to_string())),
+        "iframe" if node.attr("src").map_or(false, is_video_player) => {
+            out.push(MarkupNode::Video(node.attr("src").unwrap_or("").to_string()));
+        },
+
+        "img" => out.push(MarkupNode::Image{
+            src:    node.attr("src").unwrap_or("").to_string(),
+            alt:    node.attr("alt").unwrap_or("").to_string(),
+            title:  node.attr("title").unwrap_or("").to_string()
+        }),
+
+        // Так Табун показывает упоминание пользователя
+        "a" if has_class(node, "ls-user") => {
+            out.push(MarkupNode::UserMention(node.text().trim().to_string()));
+        },
+        // А так — место ката в полном тексте поста
+        "a" if node.attr("href").is_none() && node.attr("name") == Some("cut") => {
+            out.push(MarkupNode::Cut(None));
+        },
+        "a" if node.attr("href").is_some() => {
+            let mut children = Vec::new();
+            parse_children(node, &mut children);
+            out.push(MarkupNode::Link{
+                href:       node.attr("href").unwrap_or("").to_string(),
+                children:   children
+            });
+        },
+
+        _ if has_class(node, "spoiler") => {
+            let mut title = String::new();
+            let mut body = Vec::new();
+
+            for child in node.children() {
+                if has_class(&child, "spoiler-title") {
+                    title = child.text().trim().to_string();
+                } else if has_class(&child, "spoiler-body") {
+                    parse_children(&child, &mut body);
+                }
+            }
+
+            out.push(MarkupNode::Spoiler{
+                title:  title,
+                body:   body
+            });
+        },
+
+        _ => {
+            let attrs = match *node.data() {
+                Data::Element(_, ref attrs) => attrs.iter()
+                    .map(|&(ref k, ref v)| (k.local.to_string(), v.to_string()))
+                    .collect(),
+                _ => Vec::new()
+            };
+
+            let mut children = Vec::new();
+            parse_children(node, &mut children);
+
+            out.push(MarkupNode::Element{
+                name:       name.clone(),
+                attrs:      attrs,
+                children:   children
+            });
+        }
+    }
+}
+
+///Экранирует текст для вставки в HTML, в том числе в значения атрибутов
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn write_node(node: &MarkupNode, out: &mut String) {
+    match *node {
+        MarkupNode::Text(ref x) => out.push_str(&escape(x)),
+        MarkupNode::LineBreak => out.push_str("<br/>"),
+        MarkupNode::Cut(None) => out.push_str("<cut>"),
+        MarkupNode::Cut(Some(ref x)) => out.push_str(&format!("<cut name=\"{}\">", escape(x))),
+        MarkupNode::Spoiler{ref title, ref body} => {
+            out.push_str("<span class=\"spoiler\"><span class=\"spoiler-title\">");
+            out.push_str(&escape(title));
+            out.push_str("</span><span class=\"spoiler-body\">");
+            out.push_str(&to_html(body));
+            out.push_str("</span></span>");
+        },
+        MarkupNode::UserMention(ref x) => out.push_str(&format!("<ls user=\"{}\" />", escape(x))),
+        MarkupNode::Video(ref x) => out.push_str(&format!("<video>{}</video>", escape(x))),
+        MarkupNode::Image{ref src, ref alt, ref title} => {
+            out.push_str(&format!("<img src=\"{}\"", escape(src)));
+            if !alt.is_empty() {
+                out.push_str(&format!(" alt=\"{}\"", escape(alt)));
+            }
+            if !title.is_empty() {
+                out.push_str(&format!(" title=\"{}\"", escape(title)));
+            }
+            out.push_str(" />");
+        },
+        MarkupNode::Link{ref href, ref children} => {
+            out.push_str(&format!("<a href=\"{}\">", escape(href)));
+            out.push_str(&to_html(children));
+            out.push_str("</a>");
+        },
+        MarkupNode::Element{ref name, ref attrs, ref children} => {
+            out.push('<');
+            out.push_str(name);
+            for &(ref k, ref v) in attrs {
+                out.push_str(&format!(" {}=\"{}\"", k, escape(v)));
+            }
+
+            if VOID_ELEMENTS.contains(&name.as_str()) {
+                out.push_str(" />");
+            } else {
+                out.push('>');
+                out.push_str(&to_html(children));
+                out.push_str(&format!("</{}>", name));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::MarkupNode::*;
+
+    fn text(x: &str) -> MarkupNode {
+        Text(x.to_string())
+    }
+
+    #[test]
+    fn test_parse_raw() {
+        let tree = parse(r#"Привет, <ls user="Orhideous" />! <cut name="Дальше"><b>жирный</b>"#);
+
+        assert_eq!(tree, vec![
+            text("Привет, "),
+            UserMention("Orhideous".to_string()),
+            text("! "),
+            Cut(Some("Дальше".to_string())),
+            Element{
+                name:       "b".to_string(),
+                attrs:      Vec::new(),
+                children:   vec![text("жирный")]
+            }
+        ]);
+    }
+
+    #[test]
+    fn test_parse_rendered() {
+        let tree = parse(concat!(
+            r#"<a href="https://tabun.everypony.ru/profile/Orhideous/" class="ls-user">Orhideous</a><br/>"#,
+            r#"<a name="cut"></a>"#,
+            r#"<div class="spoiler"><div class="spoiler-title">Тайна</div><div class="spoiler-body">текст</div></div>"#,
+            r#"<iframe src="//www.youtube.com/embed/dQw4w9WgXcQ" frameborder="0"></iframe>"#,
+            r#"<img src="//cdn.everypony.ru/1.png" alt="пони">"#
+        ));
+
+        assert_eq!(tree, vec![
+            UserMention("Orhideous".to_string()),
+            LineBreak,
+            Cut(None),
+            Spoiler{
+                title:  "Тайна".to_string(),
+                body:   vec![text("текст")]
+            },
+            Video("//www.youtube.com/embed/dQw4w9WgXcQ".to_string()),
+            Image{
+                src:    "//cdn.everypony.ru/1.png".to_string(),
+                alt:    "пони".to_string(),
+                title:  String::new()
+            }
+        ]);
+    }
+
+    #[test]
+    fn test_entities() {
+        let tree = parse("a &amp; b &lt;3");
+        assert_eq!(tree, vec![text("a & b <3")]);
+        assert_eq!(to_html(&tree), "a &amp; b &lt;3");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let body = concat!(
+            r#"<a href="http://example.com">ссылка</a> <ls user="Orhideous" /> "#,
+            r#"<span class="spoiler"><span class="spoiler-title">Тайна</span><span class="spoiler-body"><i>текст</i></span></span>"#,
+            r#"<video>https://youtu.be/dQw4w9WgXcQ</video><br/><cut>"#
+        );
+
+        assert_eq!(to_html(&parse(body)), body);
+    }
+
+    #[test]
+    fn test_walk() {
+        let tree = parse(r#"<blockquote><a href="/a">раз</a> <b><a href="/b">два</a></b></blockquote>"#);
+
+        let mut links = Vec::new();
+        walk(&tree, &mut |x| if let Link{ref href, ..} = *x { links.push(href.clone()) });
+        assert_eq!(links, vec!["/a", "/b"]);
+    }
+}
@@ -0,0 +1,239 @@
+/* Media and embeds from post and comment bodies
+ *
+ * Copyright (C) 2016 TyanNN <TyanNN@cocaine.ninja>
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along
+ * with this program; if not, write to the Free Software Foundation, Inc.,
+ * 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+*/
+
+//! Картинки, видео и ссылки из текстов постов и комментов.
+//!
+//! Все ссылки приводятся к абсолютным относительно сайта, в том числе
+//! ссылки без протокола (`//cdn.everypony.ru/...`), которые Табун
+//! использует для картинок и аватаров.
+//!
+//! ```
+//! use libtabun::{markup, media};
+//!
+//! let tree = markup::parse(r#"<img src="//cdn.everypony.ru/1.png">"#);
+//! let found = media::extract(&tree, libtabun::HOST_URL);
+//! assert_eq!(found[0], media::Media::Image{
+//!     url: "https://cdn.everypony.ru/1.png".to_string(),
+//!     alt: String::new()
+//! });
+//! ```
+
+extern crate url;
+
+use super::{Post, Comment};
+
+use markup::{self, MarkupNode};
+
+use url::Url;
+
+///Сайт, на котором лежит видео
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum VideoProvider {
+    YouTube,
+    Vimeo,
+    Other
+}
+
+///Картинка, видео или ссылка из текста
+#[derive(Debug,Clone,PartialEq)]
+pub enum Media {
+    Image{
+        url:    String,
+        alt:    String
+    },
+
+    Video{
+        url:        String,
+        provider:   VideoProvider,
+
+        ///ID видео на YouTube или Vimeo, если его удалось найти
+        id:         Option<String>
+    },
+
+    Link{
+        url:    String,
+
+        ///Текст ссылки
+        text:   String
+    }
+}
+
+impl Media {
+    ///Абсолютная ссылка на картинку, видео или страницу
+    pub fn url(&self) -> &str {
+        match *self {
+            Media::Image{ref url, ..} | Media::Video{ref url, ..} | Media::Link{ref url, ..} => url
+        }
+    }
+}
+
+///Приводит ссылку к абсолютной относительно `base`, понимает ссылки
+///без протокола (`//host/path`)
+pub fn resolve_url(base: &str, url: &str) -> Option<String> {
+    let url = url.trim();
+    if url.is_empty() {
+        return None;
+    }
+
+    Url::parse(base).ok()
+        .and_then(|base| base.join(url).ok())
+        .map(|x| x.into_string())
+}
+
+///Определяет сайт и ID видео по ссылке на видео или на плеер
+pub fn video_info(url: &str) -> (VideoProvider, Option<String>) {
+    let parsed = match Url::parse(url) {
+        Ok(x)   => x,
+        Err(_)  => return (VideoProvider::Other, None)
+    };
+
+    let host = parsed.host_str().unwrap_or("").trim_left_matches("www.").trim_left_matches("m.").to_string();
+    let segments = parsed.path_segments()
+        .map(|x| x.filter(|s| !s.is_empty()).map(|s| s.to_string()).collect::<Vec<_>>())
+        .unwrap_or_else(Vec::new);
+
+    match host.as_str() {
+        "youtube.com" | "youtube-nocookie.com" => {
+            let id = if segments.get(0).map_or(false, |x| x == "embed" || x == "v") {
+                segments.get(1).cloned()
+            } else {
+                parsed.query_pairs().find(|&(ref k, _)| k == "v").map(|(_, v)| v.into_owned())
+            };
+            (VideoProvider::YouTube, id)
+        },
+        "youtu.be" => (VideoProvider::YouTube, segments.get(0).cloned()),
+        "vimeo.com" | "player.vimeo.com" => {
+            let id = segments.iter().find(|x| x.chars().all(|c| c.is_digit(10))).cloned();
+            (VideoProvider::Vimeo, id)
+        },
+        _ => (VideoProvider::Other, None)
+    }
+}
+
+///Собирает картинки, видео и ссылки из дерева в порядке появления.
+///Относительные ссылки разрешаются относительно `base`
+pub fn extract(nodes: &[MarkupNode], base: &str) -> Vec<Media> {
+    let mut ret = Vec::new();
+
+    markup::walk(nodes, &mut |node| match *node {
+        MarkupNode::Image{ref src, ref alt, ..} => {
+            if let Some(url) = resolve_url(base, src) {
+                ret.push(Media::Image{
+                    url:    url,
+                    alt:    alt.clone()
+                });
+            }
+        },
+        MarkupNode::Video(ref src) => {
+            if let Some(url) = resolve_url(base, src) {
+                let (provider, id) = video_info(&url);
+                ret.push(Media::Video{
+                    url:        url,
+                    provider:   provider,
+                    id:         id
+                });
+            }
+        },
+        // Якоря и ссылку "читать дальше" пропускаем
+        MarkupNode::Link{ref href, ref children} if !href.starts_with('#') && !href.ends_with("#cut") => {
+            if let Some(url) = resolve_url(base, href) {
+                let mut text = String::new();
+                markup::walk(children, &mut |x| if let MarkupNode::Text(ref t) = *x { text.push_str(t) });
+
+                ret.push(Media::Link{
+                    url:    url,
+                    text:   text.trim().to_string()
+                });
+            }
+        },
+        _ => {}
+    });
+
+    ret
+}
+
+impl Post {
+    ///Картинки, видео и ссылки из текста поста. `base` - адрес сайта,
+    ///с которого загружен пост, например `user.host`
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let post = user.get_post("computers", 157198).unwrap();
+    ///for m in post.media(&user.host) {
+    ///    println!("{}", m.url());
+    ///}
+    ///```
+    pub fn media(&self, base: &str) -> Vec<Media> {
+        extract(&markup::parse(&self.body), base)
+    }
+}
+
+impl Comment {
+    ///Картинки, видео и ссылки из текста коммента. `base` - адрес сайта,
+    ///с которого загружен коммент
+    pub fn media(&self, base: &str) -> Vec<Media> {
+        extract(&markup::parse(&self.body), base)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use markup;
+    use HOST_URL;
+
+    #[test]
+    fn test_resolve_url() {
+        assert_eq!(resolve_url(HOST_URL, "//cdn.everypony.ru/1.png"), Some("https://cdn.everypony.ru/1.png".to_string()));
+        assert_eq!(resolve_url(HOST_URL, "/blog/157198.html"), Some("https://tabun.everypony.ru/blog/157198.html".to_string()));
+        assert_eq!(resolve_url(HOST_URL, "http://example.com/"), Some("http://example.com/".to_string()));
+        assert_eq!(resolve_url(HOST_URL, ""), None);
+    }
+
+    #[test]
+    fn test_video_info() {
+        assert_eq!(video_info("https://www.youtube.com/embed/dQw4w9WgXcQ"), (VideoProvider::YouTube, Some("dQw4w9WgXcQ".to_string())));
+        assert_eq!(video_info("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=1"), (VideoProvider::YouTube, Some("dQw4w9WgXcQ".to_string())));
+        assert_eq!(video_info("https://youtu.be/dQw4w9WgXcQ"), (VideoProvider::YouTube, Some("dQw4w9WgXcQ".to_string())));
+        assert_eq!(video_info("https://player.vimeo.com/video/76979871"), (VideoProvider::Vimeo, Some("76979871".to_string())));
+        assert_eq!(video_info("https://coub.com/view/1"), (VideoProvider::Other, None));
+    }
+
+    #[test]
+    fn test_extract() {
+        let tree = markup::parse(concat!(
+            r#"<a href="/blog/1.html">пост</a> <a href="/blog/1.html#cut">дальше</a>"#,
+            r#"<div class="spoiler"><div class="spoiler-title">т</div><div class="spoiler-body">"#,
+            r#"<iframe src="//www.youtube.com/embed/dQw4w9WgXcQ"></iframe></div></div>"#
+        ));
+
+        assert_eq!(extract(&tree, HOST_URL), vec![
+            Media::Link{
+                url:    "https://tabun.everypony.ru/blog/1.html".to_string(),
+                text:   "пост".to_string()
+            },
+            Media::Video{
+                url:        "https://www.youtube.com/embed/dQw4w9WgXcQ".to_string(),
+                provider:   VideoProvider::YouTube,
+                id:         Some("dQw4w9WgXcQ".to_string())
+            }
+        ]);
+    }
+}
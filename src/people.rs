@@ -0,0 +1,86 @@
+/* People list
+ *
+ * Copyright (C) 2016 TyanNN <TyanNN@cocaine.ninja>
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along
+ * with this program; if not, write to the Free Software Foundation, Inc.,
+ * 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+*/
+
+extern crate select;
+
+use super::*;
+
+use select::document::Document;
+use select::predicate::{Class, Name, And};
+
+impl<'a> TClient<'a> {
+
+    ///Получает страницу списка пользователей
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///for person in user.get_people(1).unwrap().items {
+    ///    println!("{} {}", person.username, person.rating);
+    ///}
+    ///```
+    pub fn get_people(&mut self, page: u32) -> TabunResult<Page<UserItem>> {
+        let doc = try!(self.get_document(&format!("/people/index/page{}/", page)));
+        let people = try!(self.doc_get_people(&doc));
+        Ok(self.doc_get_page(&doc, people))
+    }
+
+    ///Все пользователи, страницы загружаются по мере перебора
+    pub fn people<'c>(&'c mut self) -> Listing<'c, 'a, UserItem> {
+        Listing::new(self, Box::new(|page| format!("/people/index/page{}/", page)), TClient::doc_get_people)
+    }
+
+    pub fn doc_get_people(&mut self, doc: &Document) -> TabunResult<Vec<UserItem>> {
+        let mut ret = Vec::new();
+
+        for row in doc.find(Class("table-users")).find(Name("tbody")).find(Name("tr")).iter() {
+            let username = try_to_parse!(row.find(And(Name("a"), Class("username"))).first()).text();
+
+            let realname = match row.find(Class("realname")).first() {
+                Some(x) => x.text().trim().to_string(),
+                None    => String::new()
+            };
+
+            let skill = try_to_parse!(hado!{
+                cell <- row.find(Class("cell-skill")).first();
+                cell.text().trim().replace(',', ".").parse::<f32>().ok()
+            });
+
+            let rating = try_to_parse!(hado!{
+                cell <- row.find(Class("cell-rating")).first();
+                cell.text().trim().replace(',', ".").parse::<f32>().ok()
+            });
+
+            let userpic = row.find(Name("img")).first()
+                .and_then(|x| x.attr("src"))
+                .unwrap_or("")
+                .to_string();
+
+            ret.push(UserItem{
+                username:   username.trim().to_string(),
+                realname:   realname,
+                skill:      skill,
+                rating:     rating,
+                userpic:    userpic,
+            });
+        }
+
+        Ok(ret)
+    }
+}
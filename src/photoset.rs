@@ -0,0 +1,199 @@
+/* Photosets
+ *
+ * Copyright (C) 2016 TyanNN <TyanNN@cocaine.ninja>
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along
+ * with this program; if not, write to the Free Software Foundation, Inc.,
+ * 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+*/
+
+extern crate select;
+
+use super::*;
+
+use select::document::Document;
+use select::predicate::{Attr, Name};
+
+impl<'a> TClient<'a> {
+
+    ///Создаёт фотосет в указанном блоге и возвращает его номер.
+    ///Фотографии передаются парами путь к файлу - подпись, `main` - номер
+    ///главной фотографии в этом списке. Если publish=false, то пост
+    ///сохраняется в черновиках
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let blog_id = user.get_blog_id("fanart").unwrap();
+    ///let post_id = user.add_photoset(
+    ///    blog_id, "Название поста", "Текст поста", &vec!["тэг раз","тэг два"],
+    ///    &[("images/1.png", "Первая"), ("images/2.png", "Вторая")], 0, false, true
+    ///).unwrap();
+    ///```
+    pub fn add_photoset(&mut self, blog_id: u32, title: &str, body: &str, tags: &[&str], photos: &[(&str, &str)], main: usize, forbid_comment: bool, publish: bool) -> TabunResult<u32> {
+        if main >= photos.len() {
+            return Err(TabunError::Error(
+                String::from("Ошибка"),
+                String::from("Главная фотография должна быть среди загружаемых")
+            ));
+        }
+
+        // Табун выдаёт печеньку, к которой привязываются фотографии
+        // ещё не созданного фотосета
+        try!(self.get("/photoset/add/"));
+
+        let mut main_id = 0;
+        for (i, &(path, description)) in photos.iter().enumerate() {
+            let id = try!(self.upload_photoset_photo(None, path));
+            if !description.is_empty() {
+                try!(self.set_photoset_photo_description(id, description));
+            }
+            if i == main {
+                main_id = id;
+            }
+        }
+
+        self.submit_photoset("/photoset/add/", blog_id, title, body, &tags.join(","), main_id, forbid_comment, publish)
+    }
+
+    ///Редактирует фотосет, возвращает его ID. Фотографии не трогаются,
+    ///`main_photo` - ID главной фотографии, если 0, то остаётся текущая
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let blog_id = user.get_blog_id("fanart").unwrap();
+    ///user.edit_photoset(157198, blog_id, "Новое название", "Новый текст",
+    ///                   &vec!["тэг".to_string()], 0, false, true);
+    ///```
+    pub fn edit_photoset(&mut self, post_id: u32, blog_id: u32, title: &str, body: &str, tags: &[String], main_photo: u32, forbid_comment: bool, publish: bool) -> TabunResult<u32> {
+        let url = format!("/photoset/edit/{}/", post_id);
+
+        let main_photo = if main_photo != 0 {
+            main_photo
+        } else {
+            let doc = try!(self.get_document(&url));
+            try_to_parse!(hado!{
+                el <- doc.find(Attr("id","topic_main_photo")).first();
+                id_s <- el.attr("value");
+                id_s.parse::<u32>().ok()
+            })
+        };
+
+        self.submit_photoset(&url, blog_id, title, body, &tags.join(","), main_photo, forbid_comment, publish)
+    }
+
+    ///ID фотографий фотосета в том порядке, в котором они показаны
+    ///на странице редактирования
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let photos = user.get_photoset_photo_ids(157198).unwrap();
+    ///```
+    pub fn get_photoset_photo_ids(&mut self, post_id: u32) -> TabunResult<Vec<u32>> {
+        let doc = try!(self.get_document(&format!("/photoset/edit/{}/", post_id)));
+        Ok(self.doc_get_photoset_photo_ids(&doc))
+    }
+
+    pub fn doc_get_photoset_photo_ids(&self, doc: &Document) -> Vec<u32> {
+        doc.find(Name("li")).iter()
+            .filter_map(|li| li.attr("id"))
+            .filter(|id| id.starts_with("photo_"))
+            .filter_map(|id| id["photo_".len()..].parse::<u32>().ok())
+            .collect()
+    }
+
+    ///Загружает фотографию в существующий фотосет, возвращает ID фотографии
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///user.add_photoset_photo(157198, "images/3.png", "Третья");
+    ///```
+    pub fn add_photoset_photo(&mut self, post_id: u32, path: &str, description: &str) -> TabunResult<u32> {
+        let id = try!(self.upload_photoset_photo(post_id, path));
+        if !description.is_empty() {
+            try!(self.set_photoset_photo_description(id, description));
+        }
+        Ok(id)
+    }
+
+    ///Удаляет фотографию из фотосета
+    pub fn remove_photoset_photo(&mut self, photo_id: u32) -> TabunResult<()> {
+        let photo_id = photo_id.to_string();
+        try!(self.ajax("/photoset/deleteimage/", &vec![("id", photo_id.as_str())]));
+        Ok(())
+    }
+
+    ///Меняет подпись к фотографии из фотосета
+    pub fn set_photoset_photo_description(&mut self, photo_id: u32, description: &str) -> TabunResult<()> {
+        let photo_id = photo_id.to_string();
+
+        try!(self.ajax(
+            "/photoset/setimagedescription/",
+            &vec![
+                ("id",      photo_id.as_str()),
+                ("text",    description)
+            ]
+        ));
+        Ok(())
+    }
+
+    ///Загружает фотографию в фотосет с указанным ID или, если он None,
+    ///в создаваемый фотосет. Возвращает ID фотографии
+    fn upload_photoset_photo<T: Into<Option<u32>>>(&mut self, post_id: T, path: &str) -> TabunResult<u32> {
+        let post_id = match post_id.into() {
+            Some(x) => x.to_string(),
+            None    => String::new()
+        };
+
+        let mut bd = vec![
+            ("topic_id",    MultipartValue::Text(&post_id)),
+            ("Filedata",    MultipartValue::File(path))
+        ];
+
+        let data = try!(self.ajax_with_files("/photoset/upload/", &mut bd));
+
+        match data.pointer("/id") {
+            Some(x) => {
+                // Смотря по версии, Табун отдаёт ID то числом, то строкой
+                match x.as_u64() {
+                    Some(id) => Ok(id as u32),
+                    None => Ok(try_to_parse!(x.as_str().and_then(|id| id.parse::<u32>().ok())))
+                }
+            },
+            None => Err(parse_error!("Server did not return id"))
+        }
+    }
+
+    ///Отправляет форму фотосета
+    ///(внутренний метод для публичных add_photoset и edit_photoset)
+    fn submit_photoset(&mut self, form_url: &str, blog_id: u32, title: &str, body: &str, tags: &str, main_photo: u32, forbid_comment: bool, publish: bool) -> TabunResult<u32> {
+        let blog_id = blog_id.to_string();
+        let main_photo = main_photo.to_string();
+        let forbid_comment = if forbid_comment { "1" } else { "0" };
+
+        let bd = vec![
+            ("topic_type",            "photoset"),
+            ("blog_id",               &blog_id),
+            ("topic_title",           title),
+            ("topic_text",            body),
+            ("topic_tags",            tags),
+            ("topic_main_photo",      &main_photo),
+            ("topic_forbid_comment",  forbid_comment)
+        ];
+
+        self.submit_topic_form(form_url, &bd, publish)
+    }
+}
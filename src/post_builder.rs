@@ -0,0 +1,483 @@
+/* Post builder
+ *
+ * Copyright (C) 2016 TyanNN <TyanNN@cocaine.ninja>
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along
+ * with this program; if not, write to the Free Software Foundation, Inc.,
+ * 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+*/
+
+use super::*;
+
+use std::fmt;
+
+//Ограничения Табуна на форму поста
+
+const TITLE_MIN_LENGTH: usize = 2;
+const TITLE_MAX_LENGTH: usize = 200;
+const BODY_MIN_LENGTH: usize = 2;
+const TAGS_MAX_COUNT: usize = 15;
+const TAG_MIN_LENGTH: usize = 2;
+const TAG_MAX_LENGTH: usize = 50;
+const ANSWERS_MIN_COUNT: usize = 2;
+const ANSWERS_MAX_COUNT: usize = 20;
+const ANSWER_MAX_LENGTH: usize = 100;
+
+///Проблема, найденная при проверке поста перед отправкой
+#[derive(Debug,Clone,PartialEq)]
+pub enum PostValidationError {
+    ///Заголовок короче минимума, в скобках его длина
+    TitleTooShort(usize),
+
+    ///Заголовок длиннее максимума, в скобках его длина
+    TitleTooLong(usize),
+
+    ///Текст поста слишком короткий
+    BodyTooShort(usize),
+
+    ///Нет ни одного тэга
+    NoTags,
+
+    ///Тэгов больше максимума, в скобках их кол-во
+    TooManyTags(usize),
+
+    ///Тэг слишком короткий
+    TagTooShort(String),
+
+    ///Тэг слишком длинный
+    TagTooLong(String),
+
+    ///У поста-ссылки нет ссылки
+    NoLinkUrl,
+
+    ///У фотосета нет фотографий
+    NoPhotos,
+
+    ///Номер главной фотографии больше кол-ва фотографий
+    BadMainPhoto(usize),
+
+    ///У опроса меньше вариантов ответа, чем нужно, в скобках их кол-во
+    TooFewAnswers(usize),
+
+    ///У опроса больше вариантов ответа, чем можно, в скобках их кол-во
+    TooManyAnswers(usize),
+
+    ///Пустой вариант ответа
+    EmptyAnswer,
+
+    ///Слишком длинный вариант ответа
+    AnswerTooLong(String),
+
+    ///Варианты ответа указаны не для опроса
+    AnswersNotAllowed,
+}
+
+impl fmt::Display for PostValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PostValidationError::TitleTooShort(x) =>
+                write!(f, "Заголовок слишком короткий ({} из минимум {} символов)", x, TITLE_MIN_LENGTH),
+            PostValidationError::TitleTooLong(x) =>
+                write!(f, "Заголовок слишком длинный ({} из максимум {} символов)", x, TITLE_MAX_LENGTH),
+            PostValidationError::BodyTooShort(x) =>
+                write!(f, "Текст слишком короткий ({} из минимум {} символов)", x, BODY_MIN_LENGTH),
+            PostValidationError::NoTags =>
+                write!(f, "Не указаны тэги"),
+            PostValidationError::TooManyTags(x) =>
+                write!(f, "Слишком много тэгов ({} из максимум {})", x, TAGS_MAX_COUNT),
+            PostValidationError::TagTooShort(ref x) =>
+                write!(f, "Тэг \"{}\" слишком короткий", x),
+            PostValidationError::TagTooLong(ref x) =>
+                write!(f, "Тэг \"{}\" слишком длинный", x),
+            PostValidationError::NoLinkUrl =>
+                write!(f, "Не указана ссылка"),
+            PostValidationError::NoPhotos =>
+                write!(f, "Нет ни одной фотографии"),
+            PostValidationError::BadMainPhoto(x) =>
+                write!(f, "Нет фотографии с номером {}", x),
+            PostValidationError::TooFewAnswers(x) =>
+                write!(f, "Слишком мало вариантов ответа ({} из минимум {})", x, ANSWERS_MIN_COUNT),
+            PostValidationError::TooManyAnswers(x) =>
+                write!(f, "Слишком много вариантов ответа ({} из максимум {})", x, ANSWERS_MAX_COUNT),
+            PostValidationError::EmptyAnswer =>
+                write!(f, "Пустой вариант ответа"),
+            PostValidationError::AnswerTooLong(ref x) =>
+                write!(f, "Вариант ответа \"{}\" слишком длинный", x),
+            PostValidationError::AnswersNotAllowed =>
+                write!(f, "Варианты ответа бывают только у опросов"),
+        }
+    }
+}
+
+///Проверенный пост, готовый к отправке через
+///[`submit_post`](struct.TClient.html#method.submit_post)
+#[derive(Debug,Clone)]
+pub struct PostDraft {
+    ///ID блога, 0 - личный блог
+    pub blog_id:        u32,
+    pub title:          String,
+    pub body:           String,
+    pub tags:           Vec<String>,
+    pub topic_type:     TopicType,
+
+    ///Ссылка для поста-ссылки
+    pub link_url:       String,
+
+    ///Фотографии для фотосета, пары путь к файлу - подпись
+    pub photos:         Vec<(String,String)>,
+
+    ///Номер главной фотографии фотосета
+    pub main_photo:     usize,
+
+    ///Варианты ответа для опроса
+    pub answers:        Vec<String>,
+    pub forbid_comment: bool,
+
+    ///false - сохранить в черновиках
+    pub publish:        bool,
+}
+
+///Строитель поста. Собирает все параметры поста и перед отправкой проверяет
+///их на соответствие ограничениям Табуна, возвращая сразу все найденные проблемы
+///
+///# Examples
+///```no_run
+///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+///use libtabun::{PostBuilder,TopicType};
+///
+///let blog_id = user.get_blog_id("computers").unwrap();
+///let draft = PostBuilder::new()
+///    .blog_id(blog_id)
+///    .topic_type(TopicType::Question)
+///    .title("Вопрос")
+///    .body("Текст поста")
+///    .tags(&["тэг раз", "тэг два"])
+///    .answers(&["Ответ 1", "Ответ 2"])
+///    .finalize();
+///
+///match draft {
+///    Ok(draft) => { user.submit_post(&draft).unwrap(); },
+///    Err(problems) => for p in problems { println!("{}", p); }
+///}
+///```
+pub struct PostBuilder {
+    draft: PostDraft,
+}
+
+impl PostBuilder {
+    pub fn new() -> PostBuilder {
+        PostBuilder {
+            draft: PostDraft {
+                blog_id:        0,
+                title:          String::new(),
+                body:           String::new(),
+                tags:           Vec::new(),
+                topic_type:     TopicType::Topic,
+                link_url:       String::new(),
+                photos:         Vec::new(),
+                main_photo:     0,
+                answers:        Vec::new(),
+                forbid_comment: false,
+                publish:        true,
+            }
+        }
+    }
+
+    ///Блог, в который писать пост. По умолчанию 0 - личный блог
+    pub fn blog_id(mut self, blog_id: u32) -> PostBuilder {
+        self.draft.blog_id = blog_id;
+        self
+    }
+
+    pub fn title(mut self, title: &str) -> PostBuilder {
+        self.draft.title = title.to_string();
+        self
+    }
+
+    pub fn body(mut self, body: &str) -> PostBuilder {
+        self.draft.body = body.to_string();
+        self
+    }
+
+    pub fn tags(mut self, tags: &[&str]) -> PostBuilder {
+        self.draft.tags = tags.iter().map(|x| x.to_string()).collect();
+        self
+    }
+
+    pub fn tag(mut self, tag: &str) -> PostBuilder {
+        self.draft.tags.push(tag.to_string());
+        self
+    }
+
+    pub fn topic_type(mut self, topic_type: TopicType) -> PostBuilder {
+        self.draft.topic_type = topic_type;
+        self
+    }
+
+    pub fn link_url(mut self, url: &str) -> PostBuilder {
+        self.draft.link_url = url.to_string();
+        self
+    }
+
+    pub fn photo(mut self, path: &str, description: &str) -> PostBuilder {
+        self.draft.photos.push((path.to_string(), description.to_string()));
+        self
+    }
+
+    pub fn main_photo(mut self, main_photo: usize) -> PostBuilder {
+        self.draft.main_photo = main_photo;
+        self
+    }
+
+    pub fn answers(mut self, answers: &[&str]) -> PostBuilder {
+        self.draft.answers = answers.iter().map(|x| x.to_string()).collect();
+        self
+    }
+
+    pub fn forbid_comment(mut self, forbid_comment: bool) -> PostBuilder {
+        self.draft.forbid_comment = forbid_comment;
+        self
+    }
+
+    pub fn publish(mut self, publish: bool) -> PostBuilder {
+        self.draft.publish = publish;
+        self
+    }
+
+    ///Проверяет пост и возвращает его или список всех найденных проблем
+    pub fn finalize(self) -> Result<PostDraft, Vec<PostValidationError>> {
+        let errors = self.draft.validate();
+        if errors.is_empty() {
+            Ok(self.draft)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl PostDraft {
+    ///Проверяет пост на соответствие ограничениям Табуна,
+    ///возвращает все найденные проблемы
+    pub fn validate(&self) -> Vec<PostValidationError> {
+        let mut errors = Vec::new();
+
+        let title_len = self.title.trim().chars().count();
+        if title_len < TITLE_MIN_LENGTH {
+            errors.push(PostValidationError::TitleTooShort(title_len));
+        } else if title_len > TITLE_MAX_LENGTH {
+            errors.push(PostValidationError::TitleTooLong(title_len));
+        }
+
+        // У ссылок и опросов текст необязателен
+        let body_len = self.body.trim().chars().count();
+        let body_required = match self.topic_type {
+            TopicType::Topic | TopicType::Photoset => true,
+            TopicType::Link | TopicType::Question  => false
+        };
+        if (body_required || body_len > 0) && body_len < BODY_MIN_LENGTH {
+            errors.push(PostValidationError::BodyTooShort(body_len));
+        }
+
+        let tags = self.tags.iter().map(|x| x.trim()).filter(|x| !x.is_empty()).collect::<Vec<_>>();
+        if tags.is_empty() {
+            errors.push(PostValidationError::NoTags);
+        } else if tags.len() > TAGS_MAX_COUNT {
+            errors.push(PostValidationError::TooManyTags(tags.len()));
+        }
+        for tag in tags {
+            let len = tag.chars().count();
+            if len < TAG_MIN_LENGTH {
+                errors.push(PostValidationError::TagTooShort(tag.to_string()));
+            } else if len > TAG_MAX_LENGTH {
+                errors.push(PostValidationError::TagTooLong(tag.to_string()));
+            }
+        }
+
+        match self.topic_type {
+            TopicType::Link => {
+                if self.link_url.trim().is_empty() {
+                    errors.push(PostValidationError::NoLinkUrl);
+                }
+            },
+            TopicType::Photoset => {
+                if self.photos.is_empty() {
+                    errors.push(PostValidationError::NoPhotos);
+                } else if self.main_photo >= self.photos.len() {
+                    errors.push(PostValidationError::BadMainPhoto(self.main_photo));
+                }
+            },
+            TopicType::Question => {
+                if self.answers.len() < ANSWERS_MIN_COUNT {
+                    errors.push(PostValidationError::TooFewAnswers(self.answers.len()));
+                } else if self.answers.len() > ANSWERS_MAX_COUNT {
+                    errors.push(PostValidationError::TooManyAnswers(self.answers.len()));
+                }
+                for answer in &self.answers {
+                    let len = answer.trim().chars().count();
+                    if len == 0 {
+                        errors.push(PostValidationError::EmptyAnswer);
+                    } else if len > ANSWER_MAX_LENGTH {
+                        errors.push(PostValidationError::AnswerTooLong(answer.to_string()));
+                    }
+                }
+            },
+            TopicType::Topic => {}
+        }
+
+        if self.topic_type != TopicType::Question && !self.answers.is_empty() {
+            errors.push(PostValidationError::AnswersNotAllowed);
+        }
+
+        errors
+    }
+}
+
+impl<'a> TClient<'a> {
+
+    ///Создаёт пост из [`PostDraft`](struct.PostDraft.html) и возвращает его номер.
+    ///Черновик проверяется ещё раз, так как его поля можно было заполнить
+    ///и без [`PostBuilder`](struct.PostBuilder.html); найденные проблемы
+    ///возвращаются как `TabunError::ValidationError`
+    pub fn submit_post(&mut self, draft: &PostDraft) -> TabunResult<u32> {
+        let errors = draft.validate();
+        if !errors.is_empty() {
+            return Err(TabunError::ValidationError(
+                errors.iter().map(|x| (String::from("Ошибка"), x.to_string())).collect()
+            ));
+        }
+
+        let blog_id = draft.blog_id.to_string();
+        let tags = draft.tags.join(",");
+        let forbid_comment = if draft.forbid_comment { "1" } else { "0" };
+
+        match draft.topic_type {
+            TopicType::Topic => {
+                let bd = vec![
+                    ("topic_type",            "topic"),
+                    ("blog_id",               &blog_id),
+                    ("topic_title",           &draft.title),
+                    ("topic_text",            &draft.body),
+                    ("topic_tags",            &tags),
+                    ("topic_forbid_comment",  forbid_comment)
+                ];
+
+                self.submit_topic_form("/topic/add", &bd, draft.publish)
+            },
+            TopicType::Question => {
+                let answers = draft.answers.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+                let tags = draft.tags.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+                self.add_poll(draft.blog_id, &draft.title, &answers, &draft.body, &tags, draft.forbid_comment, draft.publish)
+            },
+            TopicType::Link => {
+                let bd = vec![
+                    ("topic_type",            "link"),
+                    ("blog_id",               &blog_id),
+                    ("topic_title",           &draft.title),
+                    ("topic_link_url",        &draft.link_url),
+                    ("topic_text",            &draft.body),
+                    ("topic_tags",            &tags),
+                    ("topic_forbid_comment",  forbid_comment)
+                ];
+
+                self.submit_topic_form("/link/add", &bd, draft.publish)
+            },
+            TopicType::Photoset => {
+                let photos = draft.photos.iter().map(|&(ref p, ref d)| (p.as_str(), d.as_str())).collect::<Vec<_>>();
+                let tags = draft.tags.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+                self.add_photoset(draft.blog_id, &draft.title, &draft.body, &tags, &photos, draft.main_photo, draft.forbid_comment, draft.publish)
+            }
+        }
+    }
+
+    ///Редактирует пост с указанным ID, заменяя его содержимое на
+    ///[`PostDraft`](struct.PostDraft.html). Фотографии фотосета и варианты
+    ///ответа опроса при этом не меняются, так как Табун не даёт их так редактировать,
+    ///а `main_photo` у фотосета - номер главной среди уже загруженных фотографий
+    pub fn submit_post_edit(&mut self, post_id: u32, draft: &PostDraft) -> TabunResult<u32> {
+        match draft.topic_type {
+            TopicType::Topic => {
+                self.edit_post_ext(post_id, draft.blog_id, &draft.title, &draft.body, &draft.tags, draft.forbid_comment, draft.publish)
+            },
+            TopicType::Question => {
+                let tags = draft.tags.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+                self.edit_poll(post_id, draft.blog_id, &draft.body, &tags, draft.forbid_comment, draft.publish)
+            },
+            TopicType::Link => {
+                self.edit_link(post_id, draft.blog_id, &draft.title, &draft.link_url, &draft.body, &draft.tags, draft.forbid_comment, draft.publish)
+            },
+            TopicType::Photoset => {
+                let main_photo = match try!(self.get_photoset_photo_ids(post_id)).get(draft.main_photo) {
+                    Some(&x) => x,
+                    None     => return Err(TabunError::ValidationError(vec![(
+                        String::from("Ошибка"),
+                        PostValidationError::BadMainPhoto(draft.main_photo).to_string()
+                    )]))
+                };
+                self.edit_photoset(post_id, draft.blog_id, &draft.title, &draft.body, &draft.tags, main_photo, draft.forbid_comment, draft.publish)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_valid_post() {
+        let draft = PostBuilder::new()
+            .blog_id(193)
+            .title("Заголовок")
+            .body("Текст поста")
+            .tags(&["тэг раз", "тэг два"])
+            .finalize();
+
+        assert!(draft.is_ok());
+    }
+
+    #[test]
+    fn test_all_errors_at_once() {
+        let long_tag = ::std::iter::repeat("т").take(TAG_MAX_LENGTH + 1).collect::<String>();
+        let errors = PostBuilder::new()
+            .title("З")
+            .tags(&["т", long_tag.as_str()])
+            .answers(&["Ответ"])
+            .finalize()
+            .unwrap_err();
+
+        assert_eq!(errors, vec![
+            PostValidationError::TitleTooShort(1),
+            PostValidationError::BodyTooShort(0),
+            PostValidationError::TagTooShort("т".to_string()),
+            PostValidationError::TagTooLong(long_tag.clone()),
+            PostValidationError::AnswersNotAllowed,
+        ]);
+    }
+
+    #[test]
+    fn test_poll_answers() {
+        let answers = (0..ANSWERS_MAX_COUNT + 1).map(|x| x.to_string()).collect::<Vec<_>>();
+        let answers = answers.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+
+        let errors = PostBuilder::new()
+            .blog_id(193)
+            .topic_type(TopicType::Question)
+            .title("Вопрос")
+            .tag("опрос")
+            .answers(&answers)
+            .finalize()
+            .unwrap_err();
+
+        assert_eq!(errors, vec![PostValidationError::TooManyAnswers(ANSWERS_MAX_COUNT + 1)]);
+    }
+}
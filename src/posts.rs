@@ -24,11 +24,156 @@ extern crate hyper;
 use super::*;
 
 use select::document::Document;
+use select::selection::Selection;
+use select::node::Node;
 use select::predicate::{Name,Class,Attr,And};
-use hyper::header::Referer;
+use hyper::client::Client;
+use hyper::header::{Referer,Cookie,SetCookie};
+use hyper::status::StatusCode;
 
 use regex::Regex;
-use std::str;
+
+use std::cmp::{max, min};
+use std::io::Read;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+///Разделяет ссылки на тэги поста на обычные тэги и тэги избранного
+///текущего пользователя (последние ведут в его профиль)
+fn split_tags(links: Selection) -> (Vec<String>, Vec<String>) {
+    let mut tags = Vec::new();
+    let mut favourite_tags = Vec::new();
+
+    for a in links.iter() {
+        if a.attr("href").map_or(false, |x| x.contains("/favourites/topics/tag/")) {
+            favourite_tags.push(a.text());
+        } else {
+            tags.push(a.text());
+        }
+    }
+
+    (tags, favourite_tags)
+}
+
+///Ссылка на страницу раздела главной
+fn index_url(filter: IndexFilter, page: u32) -> String {
+    let period = |p| match p {
+        Period::Day   => "1",
+        Period::Week  => "7",
+        Period::Month => "30",
+        Period::All   => "all"
+    };
+
+    match filter {
+        IndexFilter::Good           => format!("/index/page{}/", page),
+        IndexFilter::New            => format!("/index/new/page{}/", page),
+        IndexFilter::NewAll         => format!("/index/newall/page{}/", page),
+        IndexFilter::Discussed(p)   => format!("/index/discussed/page{}/?period={}", page, period(p)),
+        IndexFilter::Top(p)         => format!("/index/top/page{}/?period={}", page, period(p))
+    }
+}
+
+///Получает тип поста и специфичные для типа данные из элемента `article`
+fn parse_topic_type(article: &Node) -> TabunResult<(TopicType, Option<TopicLink>, Vec<Photo>)> {
+    let class = try_to_parse!(article.attr("class"));
+
+    let typ = if class.contains("topic-type-question") {
+        TopicType::Question
+    } else if class.contains("topic-type-link") {
+        TopicType::Link
+    } else if class.contains("topic-type-photoset") {
+        TopicType::Photoset
+    } else {
+        TopicType::Topic
+    };
+
+    let link = match typ {
+        TopicType::Link => {
+            let a = try_to_parse!(article.find(Class("topic-url")).find(Name("a")).first());
+
+            // Кол-во переходов есть только во всплывающей подсказке
+            let clicks = hado!{
+                title <- a.attr("title");
+                reg <- Regex::new(r"(\d+)\s*$").ok();
+                captures <- reg.captures(title);
+                at <- captures.at(1);
+                at.parse::<u32>().ok()
+            }.unwrap_or(0);
+
+            Some(TopicLink{
+                url:    a.text().trim().to_string(),
+                clicks: clicks
+            })
+        },
+        _ => None
+    };
+
+    let photos = article.find(And(Name("a"),Class("photoset-image"))).iter()
+        .filter_map(|a| a.attr("href").map(|href| Photo{
+            url:            href.to_string(),
+            description:    a.attr("title").unwrap_or("").to_string()
+        }))
+        .collect::<Vec<_>>();
+
+    Ok((typ, link, photos))
+}
+
+///Ссылка на страницу редактирования поста указанного типа
+fn edit_url(typ: TopicType, post_id: u32) -> String {
+    match typ {
+        TopicType::Topic    => format!("/topic/edit/{}",post_id),
+        TopicType::Question => format!("/question/edit/{}",post_id),
+        TopicType::Link     => format!("/link/edit/{}",post_id),
+        TopicType::Photoset => format!("/photoset/edit/{}/",post_id)
+    }
+}
+
+///Тип поста по его названию в формах Табуна
+fn topic_type_by_name(name: &str) -> Option<TopicType> {
+    match name {
+        "topic"     => Some(TopicType::Topic),
+        "question"  => Some(TopicType::Question),
+        "link"      => Some(TopicType::Link),
+        "photoset"  => Some(TopicType::Photoset),
+        _           => None
+    }
+}
+
+///ID блога, выбранного в форме поста. У личного блога (`value="0"`) нет
+///`selected`, браузер просто показывает первый вариант, так что без
+///выбранного варианта берётся первый
+fn selected_blog_id(doc: &Document) -> Option<u32> {
+    hado!{
+        blogs <- doc.find(And(Name("select"),Attr("id","blog_id"))).first();
+        option <- {
+            let options = blogs.find(Name("option"));
+            options.iter().find(|x| x.attr("selected").is_some()).or_else(|| options.first())
+        };
+        id_s <- option.attr("value");
+        id_s.parse::<u32>().ok()
+    }
+}
+
+///Загружает страницу из рабочего потока через общий клиент. Возвращает
+///текст и заголовок Set-Cookie, чтобы печеньки обновил основной поток
+fn fetch_page(client: &Client, url: &str, cookie: Cookie) -> TabunResult<(String, Option<SetCookie>)> {
+    let mut res = try!(client.get(url).header(cookie).send());
+
+    if res.status != hyper::Ok {
+        return Err(TabunError::from(res.status));
+    }
+
+    let mut buf = Vec::new();
+    try!(res.read_to_end(&mut buf));
+
+    Ok((String::from_utf8_lossy(&buf).into_owned(), res.headers.get::<SetCookie>().cloned()))
+}
+
+///Проверяет, помечен ли заголовок поста как черновик
+fn is_draft(title: &Node) -> bool {
+    title.find(Class("icon-synio-topic-draft")).first().is_some() ||
+        title.find(Class("topic-unpublish")).first().is_some()
+}
 
 impl<'a> TClient<'a> {
 
@@ -41,8 +186,24 @@ impl<'a> TClient<'a> {
     ///let post_id = user.add_post(blog_id,"Название поста","Текст поста",&vec!["тэг раз","тэг два"]).unwrap();
     ///```
     pub fn add_post(&mut self, blog_id: u32, title: &str, body: &str, tags: &[&str]) -> TabunResult<u32> {
+        self.submit_regular_post(blog_id, title, body, tags, true)
+    }
+
+    ///Сохраняет пост в черновиках указанного блога и возвращает его номер,
+    ///опубликовать его потом можно через [`publish_draft`](#method.publish_draft)
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let blog_id = user.get_blog_id("computers").unwrap();
+    ///let draft_id = user.add_draft(blog_id,"Название поста","Текст поста",&vec!["тэг раз","тэг два"]).unwrap();
+    ///```
+    pub fn add_draft(&mut self, blog_id: u32, title: &str, body: &str, tags: &[&str]) -> TabunResult<u32> {
+        self.submit_regular_post(blog_id, title, body, tags, false)
+    }
+
+    fn submit_regular_post(&mut self, blog_id: u32, title: &str, body: &str, tags: &[&str], publish: bool) -> TabunResult<u32> {
         let blog_id = blog_id.to_string();
-        let key = self.security_ls_key.to_owned();
         let tags = tags.join(",");
 
         let bd = vec![
@@ -50,15 +211,33 @@ impl<'a> TClient<'a> {
             ("blog_id",               &blog_id),
             ("topic_title",           title),
             ("topic_text",            body),
-            ("topic_tags",            &tags),
-            ("submit_topic_publish",  "Опубликовать"),
-            ("security_ls_key",       &key)
+            ("topic_tags",            &tags)
         ];
 
-        let res = try!(self.post_multipart("/topic/add", &bd));
+        self.submit_topic_form("/topic/add", &bd, publish)
+    }
+
+    ///Пробный запуск [`add_post`](#method.add_post): ничего не создаёт,
+    ///а возвращает HTML предпросмотра поста (с заголовком и тэгами) в том
+    ///виде, в котором его отрисует Табун
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let blog_id = user.get_blog_id("computers").unwrap();
+    ///let html = user.add_post_dry_run(blog_id,"Название поста","Текст поста",&vec!["тэг раз","тэг два"]).unwrap();
+    ///```
+    pub fn add_post_dry_run(&mut self, blog_id: u32, title: &str, body: &str, tags: &[&str]) -> TabunResult<String> {
+        let blog_id = blog_id.to_string();
+        let tags = tags.join(",");
 
-        let r = str::from_utf8(&res.headers.get_raw("location").unwrap()[0]).unwrap();
-        parse_text_to_res!(regex => r"(\d+).html$", st => r, num => 1, typ => u32 )
+        self.preview("/ajax/preview/topic/", &[
+            ("topic_type",            "topic"),
+            ("blog_id",               &blog_id),
+            ("topic_title",           title),
+            ("topic_text",            body),
+            ("topic_tags",            &tags)
+        ])
     }
 
     ///Создаёт опрос в указанном блоге и возвращает его номер
@@ -76,7 +255,6 @@ impl<'a> TClient<'a> {
     ///```
     pub fn add_poll(&mut self, blog_id: u32, title: &str, choices: &[&str], body: &str, tags: &[&str], forbid_comment: bool, publish: bool) -> TabunResult<u32> {
         let blog_id = blog_id.to_string();
-        let key = self.security_ls_key.to_owned();
         let tags = tags.join(",");
         let forbid_comment = if forbid_comment { "1" } else { "0" };
 
@@ -86,24 +264,14 @@ impl<'a> TClient<'a> {
             ("topic_title",           title),
             ("topic_text",            body),
             ("topic_tags",            &tags),
-            ("topic_forbid_comment",  forbid_comment),
-            ("security_ls_key",       &key)
+            ("topic_forbid_comment",  forbid_comment)
         ];
 
         for choice in choices {
             bd.push(("answer[]", choice));
         }
 
-        if publish {
-            bd.push(("submit_topic_publish", "Опубликовать"));
-        } else {
-            bd.push(("submit_topic_save", "Сохранить в черновиках"));
-        }
-
-        let res = try!(self.post_multipart("/question/add", &bd));
-
-        let r = str::from_utf8(&res.headers.get_raw("location").unwrap()[0]).unwrap();
-        parse_text_to_res!(regex => r"(\d+).html$", st => r, num => 1, typ => u32 )
+        self.submit_topic_form("/question/add", &bd, publish)
     }
 
     ///Получает посты из блога
@@ -113,9 +281,153 @@ impl<'a> TClient<'a> {
     ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
     ///user.get_posts("lighthouse",1);
     ///```
-    pub fn get_posts(&mut self, blog_name: &str, page: u32) -> TabunResult<Vec<Post>> {
+    pub fn get_posts(&mut self, blog_name: &str, page: u32) -> TabunResult<Page<Post>> {
         let doc = try!(self.get_document(&format!("/blog/{}/page{}/", blog_name, page)));
-        self.doc_get_posts(&doc)
+        let posts = try!(self.doc_get_posts(&doc));
+        Ok(self.doc_get_page(&doc, posts))
+    }
+
+    ///Получает посты из блога сразу с полными текстами, см.
+    ///[`fetch_full_bodies`](#method.fetch_full_bodies)
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///for post in user.get_posts_full("lighthouse", 1, 4).unwrap().items {
+    ///    assert!(!post.has_cut);
+    ///}
+    ///```
+    pub fn get_posts_full(&mut self, blog_name: &str, page: u32, threads: usize) -> TabunResult<Page<Post>> {
+        let mut posts = try!(self.get_posts(blog_name, page));
+        posts.items = try!(self.fetch_full_bodies(posts.items, threads));
+        Ok(posts)
+    }
+
+    ///Догружает полные тексты постов, обрезанных катом (с `has_cut`), в
+    ///`threads` потоков через клиент и хост, с которыми создан TClient.
+    ///Подходит для постов из любого списка: остальные поля, например
+    ///`favourite_tags`, остаются как были
+    pub fn fetch_full_bodies(&mut self, mut posts: Vec<Post>, threads: usize) -> TabunResult<Vec<Post>> {
+        let cookie = Cookie::from_cookie_jar(&self.cookies);
+
+        let ids = posts.iter()
+            .filter(|x| x.has_cut)
+            .map(|x| x.id)
+            .collect::<Vec<_>>();
+
+        // Потоки берут ID из общей очереди, так что медленная страница
+        // задерживает только свой поток
+        let (job_tx, job_rx) = mpsc::channel();
+        for &id in &ids {
+            let _ = job_tx.send(id);
+        }
+        drop(job_tx);
+        let job_rx = Arc::new(Mutex::new(job_rx));
+
+        let (res_tx, res_rx) = mpsc::channel();
+        for _ in 0..min(max(threads, 1), ids.len()) {
+            let jobs = job_rx.clone();
+            let results = res_tx.clone();
+            let client = self.client.clone();
+            let host = self.host.clone();
+            let cookie = cookie.clone();
+
+            thread::spawn(move || loop {
+                let id = match jobs.lock() {
+                    Ok(x)   => match x.recv() {
+                        Ok(id)  => id,
+                        Err(_)  => break
+                    },
+                    Err(_)  => break
+                };
+
+                let url = format!("{}/blog/{}.html", host, id);
+                if results.send((id, fetch_page(&client, &url, cookie.clone()))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(res_tx);
+
+        // Страницы качаются параллельно, а парсятся здесь по мере готовности
+        let mut done = 0;
+        for (id, res) in res_rx.iter() {
+            let (page, set_cookie) = try!(res);
+            if let Some(x) = set_cookie {
+                x.apply_to_cookie_jar(&mut self.cookies);
+            }
+
+            let full = try!(self.doc_get_post(&Document::from(page.as_str())));
+
+            for post in posts.iter_mut().filter(|x| x.id == id) {
+                post.body = full.body.clone();
+                post.photos = full.photos.clone();
+                post.has_cut = false;
+            }
+            done += 1;
+        }
+
+        if done < ids.len() {
+            return Err(TabunError::Error(
+                String::from("Ошибка"),
+                String::from("Поток, загружавший посты, упал")
+            ));
+        }
+
+        Ok(posts)
+    }
+
+    ///Получает посты из раздела главной страницы
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///use libtabun::{IndexFilter,Period};
+    ///user.get_index_posts(IndexFilter::Top(Period::Week), 1);
+    ///```
+    pub fn get_index_posts(&mut self, filter: IndexFilter, page: u32) -> TabunResult<Page<Post>> {
+        let doc = try!(self.get_document(&index_url(filter, page)));
+        let posts = try!(self.doc_get_posts(&doc));
+        Ok(self.doc_get_page(&doc, posts))
+    }
+
+    ///Все посты блога, страницы загружаются по мере перебора
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///for post in user.posts("fanart").limit(100) {
+    ///    println!("{}", post.unwrap().title);
+    ///}
+    ///```
+    pub fn posts<'c>(&'c mut self, blog_name: &str) -> Listing<'c, 'a, Post> {
+        let blog_name = blog_name.to_string();
+        Listing::new(self, Box::new(move |page| format!("/blog/{}/page{}/", blog_name, page)), TClient::doc_get_posts)
+    }
+
+    ///Все посты из раздела главной страницы, см. [`posts`](#method.posts)
+    pub fn index_posts<'c>(&'c mut self, filter: IndexFilter) -> Listing<'c, 'a, Post> {
+        Listing::new(self, Box::new(move |page| index_url(filter, page)), TClient::doc_get_posts)
+    }
+
+    ///Получает черновики текущего пользователя
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///for draft in user.get_drafts(1).unwrap().items {
+    ///    user.publish_draft(draft.id).unwrap();
+    ///}
+    ///```
+    pub fn get_drafts(&mut self, page: u32) -> TabunResult<Page<Post>> {
+        let doc = try!(self.get_document(&format!("/topic/saved/page{}/", page)));
+        let posts = try!(self.doc_get_posts(&doc));
+        Ok(self.doc_get_page(&doc, posts))
+    }
+
+    ///Публикует пост из черновиков, ничего в нём не меняя, возвращает его ID
+    pub fn publish_draft(&mut self, post_id: u32) -> TabunResult<u32> {
+        self.update_post(post_id, |post| post.publish = true)
     }
 
     ///Получает посты со страницы
@@ -137,18 +449,24 @@ impl<'a> TClient<'a> {
                 id_s.parse::<u32>().ok()
             });
 
-            let post_title = try_to_parse!(p.find(And(Name("h1"),Class("topic-title"))).first()).text();
+            let (topic_type, link, photos) = try!(parse_topic_type(&p));
+
+            let post_title = try_to_parse!(p.find(And(Name("h1"),Class("topic-title"))).first());
+            let post_draft = is_draft(&post_title);
+            let post_title = post_title.text();
 
             let post_body = try_to_parse!(p.find(And(Name("div"),Class("topic-content"))).first()).inner_html();
             let post_body = post_body.trim();
 
+            // Если текст обрезан, то после вступления стоит ссылка "читать дальше";
+            // ссылки на #cut в самом тексте не считаются
+            let has_cut = p.find(Class("topic-more")).first().is_some() ||
+                p.find(And(Name("a"),Class("read-more"))).first().is_some();
+
             let post_date = try_to_parse!(p.find(And(Name("li"),Class("topic-info-date"))).find(Name("time")).first());
             let post_date = try_to_parse!(post_date.attr("datetime"));
 
-            let post_tags = p.find(And(Name("a"),Attr("rel","tag"))).iter().fold(Vec::new(), |mut acc, x| {
-                acc.push(x.text());
-                acc
-            });
+            let (post_tags, favourite_tags) = split_tags(p.find(And(Name("a"),Attr("rel","tag"))));
 
             let cm_count = try_to_parse!(hado!{
                 el <- p.find(And(Name("li"),Class("topic-info-comments"))).first();
@@ -164,14 +482,22 @@ impl<'a> TClient<'a> {
                     body:           post_body.to_owned(),
                     date:           post_date.to_owned(),
                     tags:           post_tags,
+                    favourite_tags: favourite_tags,
                     comments_count: cm_count,
                     author:         post_author,
-                    id:             post_id, });
+                    id:             post_id,
+                    draft:          post_draft,
+                    has_cut:        has_cut,
+                    topic_type:     topic_type,
+                    link:           link,
+                    photos:         photos, });
         }
         Ok(ret)
     }
 
-    ///Получает EditablePost со страницы редактирования поста
+    ///Получает EditablePost со страницы редактирования поста любого типа.
+    ///У каждого типа своя страница редактирования, поэтому для обычного поста
+    ///нужен один запрос, а для остальных - по одному на каждый перебранный тип
     ///
     ///# Examples
     ///```no_run
@@ -179,10 +505,28 @@ impl<'a> TClient<'a> {
     ///user.get_editable_post(1111);
     ///```
     pub fn get_editable_post(&mut self, post_id: u32) -> TabunResult<EditablePost> {
-        let doc = try!(self.get_document(&format!("/topic/edit/{}",post_id)));
-        self.doc_get_editable_post(&doc)
+        let types = [TopicType::Topic, TopicType::Question, TopicType::Link, TopicType::Photoset];
+
+        for &typ in &types {
+            // Страница редактирования другого типа отвечает 404
+            match self.get_document(&edit_url(typ, post_id)) {
+                Ok(doc)                                         => {
+                    let mut ret = try!(self.doc_get_editable_post(&doc));
+                    ret.topic_type = typ;
+                    return Ok(ret);
+                },
+                Err(TabunError::NumError(StatusCode::NotFound)) => continue,
+                Err(x)                                          => return Err(x)
+            }
+        }
+
+        Err(TabunError::NumError(StatusCode::NotFound))
     }
 
+    ///Парсит страницу редактирования поста. Тип поста берётся из скрытого
+    ///поля формы, а если его нет, угадывается по полям для ссылки, фотографий
+    ///и вариантов ответа. Черновик можно отличить по кнопке отправки: у него
+    ///она называется "Опубликовать", у опубликованного поста - иначе
     pub fn doc_get_editable_post(&self, doc: &Document) -> TabunResult<EditablePost> {
         let title = try_to_parse!(doc.find(Attr("id","topic_title")).first());
         let title = try_to_parse!(title.attr("value")).to_string();
@@ -193,10 +537,45 @@ impl<'a> TClient<'a> {
         let tags = try_to_parse!(tags.attr("value"))
             .split(',').map(|x| x.to_string()).collect::<Vec<String>>();
 
+        let blog_id = try_to_parse!(selected_blog_id(doc));
+
+        let forbid_comment = match doc.find(Attr("id","topic_forbid_comment")).first() {
+            Some(x) => x.attr("checked").is_some(),
+            None    => false
+        };
+
+        let link_url = doc.find(Attr("id","topic_link_url")).first()
+            .and_then(|x| x.attr("value").map(|x| x.to_string()));
+
+        let topic_type_field = doc.find(And(Name("input"),Attr("name","topic_type"))).first()
+            .and_then(|x| x.attr("value"));
+
+        let topic_type = if let Some(x) = topic_type_field.and_then(topic_type_by_name) {
+            x
+        } else if link_url.is_some() {
+            TopicType::Link
+        } else if doc.find(Attr("id","topic_main_photo")).first().is_some() {
+            TopicType::Photoset
+        } else if doc.find(Attr("name","answer[]")).first().is_some() {
+            TopicType::Question
+        } else {
+            TopicType::Topic
+        };
+
+        let publish = match doc.find(Attr("id","submit_topic_publish")).first() {
+            Some(x) => !x.text().contains("Опубликовать"),
+            None    => true
+        };
+
         Ok(EditablePost{
-            title:  title,
-            body:   body,
-            tags:   tags
+            title:          title,
+            body:           body,
+            tags:           tags,
+            blog_id:        blog_id,
+            forbid_comment: forbid_comment,
+            topic_type:     topic_type,
+            link_url:       link_url.unwrap_or_else(String::new),
+            publish:        publish,
         })
     }
 
@@ -210,13 +589,47 @@ impl<'a> TClient<'a> {
     ///user.get_editable_poll(1111);
     ///```
     pub fn get_editable_poll(&mut self, post_id: u32) -> TabunResult<EditablePost> {
-        let doc = try!(self.get_document(&format!("/question/edit/{}",post_id)));
-        self.doc_get_editable_poll(&doc)
+        // TODO: всё-таки получить варианты ответов
+        self.get_editable_post(post_id)
     }
 
     pub fn doc_get_editable_poll(&self, doc: &Document) -> TabunResult<EditablePost> {
-        // TODO: всё-таки получить варианты ответов
-        self.doc_get_editable_post(doc)
+        let mut ret = try!(self.doc_get_editable_post(doc));
+        ret.topic_type = TopicType::Question;
+        Ok(ret)
+    }
+
+    ///Получает пост со страницы редактирования, даёт его изменить переданной
+    ///функции и отправляет обратно, возвращает ID поста. Всё, что функция
+    ///не тронула (блог, запрет комментариев, черновик или нет), остаётся как было
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///user.update_post(157198, |post| {
+    ///    post.tags.push("новый тэг".to_string());
+    ///    post.body.push_str("\n\nUPD: исправлено");
+    ///}).unwrap();
+    ///```
+    pub fn update_post<F: FnOnce(&mut EditablePost)>(&mut self, post_id: u32, f: F) -> TabunResult<u32> {
+        let mut post = try!(self.get_editable_post(post_id));
+        f(&mut post);
+
+        match post.topic_type {
+            TopicType::Topic => {
+                self.edit_post_ext(post_id, post.blog_id, &post.title, &post.body, &post.tags, post.forbid_comment, post.publish)
+            },
+            TopicType::Question => {
+                let tags = post.tags.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+                self.edit_poll(post_id, post.blog_id, &post.body, &tags, post.forbid_comment, post.publish)
+            },
+            TopicType::Link => {
+                self.edit_link(post_id, post.blog_id, &post.title, &post.link_url, &post.body, &post.tags, post.forbid_comment, post.publish)
+            },
+            TopicType::Photoset => {
+                self.edit_photoset(post_id, post.blog_id, &post.title, &post.body, &post.tags, 0, post.forbid_comment, post.publish)
+            }
+        }
     }
 
     ///Получает пост, блог можно опустить (передать `None`), но лучше так не делать,
@@ -245,7 +658,12 @@ impl<'a> TClient<'a> {
             id_s.parse::<u32>().ok()
         });
 
-        let post_title = try_to_parse!(doc.find(And(Name("h1"),Class("topic-title"))).first()).text();
+        let article = try_to_parse!(doc.find(Name("article")).first());
+        let (topic_type, link, photos) = try!(parse_topic_type(&article));
+
+        let post_title = try_to_parse!(doc.find(And(Name("h1"),Class("topic-title"))).first());
+        let post_draft = is_draft(&post_title);
+        let post_title = post_title.text();
 
         let post_body = try_to_parse!(doc.find(And(Name("div"),Class("topic-content"))).first()).inner_html();
         let post_body = post_body.trim();
@@ -255,10 +673,7 @@ impl<'a> TClient<'a> {
                                       .first());
         let post_date = try_to_parse!(post_date.attr("datetime"));
 
-        let post_tags = doc.find(And(Name("a"),Attr("rel","tag"))).iter().fold(Vec::new(),|mut acc,t| {
-            acc.push(t.text());
-            acc
-        });
+        let (post_tags, favourite_tags) = split_tags(doc.find(And(Name("a"),Attr("rel","tag"))));
 
         let cm_count = try_to_parse!(hado!{
             el <- doc.find(And(Name("span"),Attr("id","count-comments"))).first();
@@ -274,9 +689,15 @@ impl<'a> TClient<'a> {
             body:           post_body.to_owned(),
             date:           post_date.to_owned(),
             tags:           post_tags,
+            favourite_tags: favourite_tags,
             comments_count: cm_count,
             author:         post_author,
             id:             post_id,
+            draft:          post_draft,
+            has_cut:        false,
+            topic_type:     topic_type,
+            link:           link,
+            photos:         photos,
         })
     }
 
@@ -289,8 +710,20 @@ impl<'a> TClient<'a> {
     ///user.edit_post(157198,blog_id,"Новое название", "Новый текст", &vec!["тэг".to_string()],false);
     ///```
     pub fn edit_post(&mut self, post_id: u32, blog_id: u32, title: &str, body: &str, tags: &[String], forbid_comment: bool) -> TabunResult<u32> {
+        self.edit_post_ext(post_id, blog_id, title, body, tags, forbid_comment, true)
+    }
+
+    ///То же, что [`edit_post`](#method.edit_post), но если publish=false,
+    ///то пост сохраняется в черновиках (или остаётся в них)
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let blog_id = user.get_blog_id("computers").unwrap();
+    ///user.edit_post_ext(157198,blog_id,"Новое название", "Новый текст", &vec!["тэг".to_string()],false,false);
+    ///```
+    pub fn edit_post_ext(&mut self, post_id: u32, blog_id: u32, title: &str, body: &str, tags: &[String], forbid_comment: bool, publish: bool) -> TabunResult<u32> {
         let blog_id = blog_id.to_string();
-        let key = self.security_ls_key.to_owned();
         let forbid_comment = if forbid_comment { "1" } else { "0" };
         let tags = tags.join(",");
 
@@ -300,17 +733,10 @@ impl<'a> TClient<'a> {
             ("topic_title",           title),
             ("topic_text",            body),
             ("topic_tags",            &tags),
-            ("submit_topic_publish",  "Опубликовать"),
-            ("security_ls_key",       &key),
             ("topic_forbid_comment",  &forbid_comment)
         ];
 
-        let res = try!(self.post_multipart(&format!("/topic/edit/{}",post_id), &bd));
-
-        let r = str::from_utf8(&res.headers.get_raw("location").unwrap()[0]).unwrap();
-
-        parse_text_to_res!(regex => r"(\d+).html$", st => r, num => 1, typ => u32)
-
+        self.submit_topic_form(&format!("/topic/edit/{}",post_id), &bd, publish)
     }
 
     ///Редактирует опрос, возвращает его ID. Редактирование заголовка и
@@ -325,32 +751,67 @@ impl<'a> TClient<'a> {
     ///```
     pub fn edit_poll(&mut self, post_id: u32, blog_id: u32, body: &str, tags: &[&str], forbid_comment: bool, publish: bool) -> TabunResult<u32> {
         let blog_id = blog_id.to_string();
-        let key = self.security_ls_key.to_owned();
         let forbid_comment = if forbid_comment { "1" } else { "0" };
         let tags = tags.join(",");
 
-        let mut bd = vec![
+        let bd = vec![
             ("topic_type",            "question"),
             ("blog_id",               &blog_id),
             ("topic_title",           ""),
             ("topic_text",            body),
             ("topic_tags",            &tags),
-            ("security_ls_key",       &key),
             ("topic_forbid_comment",  &forbid_comment)
         ];
 
-        if publish {
-            bd.push(("submit_topic_publish", "Опубликовать"));
-        } else {
-            bd.push(("submit_topic_save", "Сохранить в черновиках"));
-        }
+        self.submit_topic_form(&format!("/question/edit/{}",post_id), &bd, publish)
+    }
 
-        let res = try!(self.post_multipart(&format!("/question/edit/{}",post_id), &bd));
+    ///Создаёт пост-ссылку в указанном блоге и возвращает его номер,
+    ///если publish=false, то пост сохраняется в черновиках
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let blog_id = user.get_blog_id("computers").unwrap();
+    ///let post_id = user.add_link(
+    ///    blog_id, "Название поста", "https://github.com/TyanNN/libtabun.rs",
+    ///    "Описание ссылки", &vec!["тэг раз","тэг два"], true
+    ///).unwrap();
+    ///```
+    pub fn add_link(&mut self, blog_id: u32, title: &str, url: &str, body: &str, tags: &[&str], publish: bool) -> TabunResult<u32> {
+        self.submit_link("/link/add", blog_id, title, url, body, &tags.join(","), false, publish)
+    }
 
-        let r = str::from_utf8(&res.headers.get_raw("location").unwrap()[0]).unwrap();
+    ///Редактирует пост-ссылку, возвращает его ID
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let blog_id = user.get_blog_id("computers").unwrap();
+    ///user.edit_link(157198, blog_id, "Новое название", "https://tabun.everypony.ru",
+    ///               "Новый текст", &vec!["тэг".to_string()], false, true);
+    ///```
+    pub fn edit_link(&mut self, post_id: u32, blog_id: u32, title: &str, url: &str, body: &str, tags: &[String], forbid_comment: bool, publish: bool) -> TabunResult<u32> {
+        self.submit_link(&format!("/link/edit/{}",post_id), blog_id, title, url, body, &tags.join(","), forbid_comment, publish)
+    }
 
-        parse_text_to_res!(regex => r"(\d+).html$", st => r, num => 1, typ => u32)
+    ///Отправляет форму поста-ссылки
+    ///(внутренний метод для публичных add_link и edit_link)
+    fn submit_link(&mut self, form_url: &str, blog_id: u32, title: &str, url: &str, body: &str, tags: &str, forbid_comment: bool, publish: bool) -> TabunResult<u32> {
+        let blog_id = blog_id.to_string();
+        let forbid_comment = if forbid_comment { "1" } else { "0" };
 
+        let bd = vec![
+            ("topic_type",            "link"),
+            ("blog_id",               &blog_id),
+            ("topic_title",           title),
+            ("topic_link_url",        url),
+            ("topic_text",            body),
+            ("topic_tags",            tags),
+            ("topic_forbid_comment",  forbid_comment)
+        ];
+
+        self.submit_topic_form(form_url, &bd, publish)
     }
 
     ///Удаляет пост, и, так как табун ничего не возаращет по этому поводу,
@@ -375,11 +836,42 @@ impl<'a> TClient<'a> {
     pub fn favourite_post(&mut self, id: u32, typ: bool) -> TabunResult<u32> {
         self.favourite(id, typ, false)
     }
+
+    ///Задаёт тэги избранного для поста, который уже в избранном,
+    ///возвращает сохранённые тэги. Текущие тэги есть в `Post.favourite_tags`
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///user.favourite_post(12345, true).unwrap();
+    ///user.set_favourite_post_tags(12345, &["музыка", "почитать"]);
+    ///```
+    pub fn set_favourite_post_tags(&mut self, id: u32, tags: &[&str]) -> TabunResult<Vec<String>> {
+        self.set_favourite_tags(id, tags, false)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use ::TClient;
+    use super::selected_blog_id;
+    use select::document::Document;
+
+    #[test]
+    fn test_selected_blog_id() {
+        let personal = Document::from(concat!(
+            r#"<select name="blog_id" id="blog_id"><option value="0">Мой блог</option>"#,
+            r#"<option value="193">Computers</option></select>"#
+        ));
+        let selected = Document::from(concat!(
+            r#"<select name="blog_id" id="blog_id"><option value="0">Мой блог</option>"#,
+            r#"<option value="193" selected>Computers</option></select>"#
+        ));
+
+        assert_eq!(selected_blog_id(&personal), Some(0));
+        assert_eq!(selected_blog_id(&selected), Some(193));
+        assert_eq!(selected_blog_id(&Document::from("<div></div>")), None);
+    }
 
     #[test]
     fn test_get_post() {
@@ -0,0 +1,256 @@
+/* Common read interface for online and offline clients
+ *
+ * Copyright (C) 2016 TyanNN <TyanNN@cocaine.ninja>
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along
+ * with this program; if not, write to the Free Software Foundation, Inc.,
+ * 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+*/
+
+//! Общий интерфейс чтения для [`TClient`](../struct.TClient.html) и, с фичей
+//! `archive`, для [`OfflineClient`](struct.OfflineClient.html), который
+//! отвечает на те же запросы из локального [архива](../archive/index.html).
+//! Код, которому достаточно читать, может принимать `&mut Reader` и
+//! работать одинаково с Табуном и без него.
+//!
+//! ```no_run
+//! use libtabun::reader::Reader;
+//!
+//! fn print_blog(reader: &mut Reader, blog: &str) {
+//!     for post in reader.get_posts(blog, 1).unwrap().items {
+//!         println!("{} - {}", post.id, post.title);
+//!     }
+//! }
+//!
+//! let mut user = libtabun::TClient::new(None, None).unwrap();
+//! print_blog(&mut user, "lighthouse");
+//! ```
+//!
+//! С фичей `archive` источник можно выбрать через [`open`](fn.open.html).
+
+use super::{TClient, TabunResult, Post, Comment, UserInfo, Page};
+
+use std::collections::HashMap;
+
+#[cfg(feature = "archive")] use super::TabunError;
+#[cfg(feature = "archive")] use archive::Archive;
+#[cfg(feature = "archive")] use hyper::status::StatusCode;
+#[cfg(feature = "archive")] use regex::Regex;
+#[cfg(feature = "archive")] use std::path::Path;
+
+///Запросы на чтение, общие для клиента Табуна и архива
+pub trait Reader {
+    ///См. [`TClient::get_post`](../struct.TClient.html#method.get_post)
+    fn get_post(&mut self, blog_name: &str, post_id: u32) -> TabunResult<Post>;
+
+    ///См. [`TClient::get_posts`](../struct.TClient.html#method.get_posts)
+    fn get_posts(&mut self, blog_name: &str, page: u32) -> TabunResult<Page<Post>>;
+
+    ///Комменты поста по ссылке на него, например `/blog/lighthouse/157807.html`,
+    ///см. [`TClient::get_comments`](../struct.TClient.html#method.get_comments)
+    fn get_comments(&mut self, url: &str) -> TabunResult<HashMap<u32, Comment>>;
+
+    ///См. [`TClient::get_profile`](../struct.TClient.html#method.get_profile)
+    fn get_profile(&mut self, name: &str) -> TabunResult<UserInfo>;
+}
+
+impl<'a> Reader for TClient<'a> {
+    fn get_post(&mut self, blog_name: &str, post_id: u32) -> TabunResult<Post> {
+        TClient::get_post(self, blog_name, post_id)
+    }
+
+    fn get_posts(&mut self, blog_name: &str, page: u32) -> TabunResult<Page<Post>> {
+        TClient::get_posts(self, blog_name, page)
+    }
+
+    fn get_comments(&mut self, url: &str) -> TabunResult<HashMap<u32, Comment>> {
+        TClient::get_comments(self, url)
+    }
+
+    fn get_profile(&mut self, name: &str) -> TabunResult<UserInfo> {
+        TClient::get_profile(self, name)
+    }
+}
+
+///Сколько постов Табун показывает на странице блога
+#[cfg(feature = "archive")]
+const POSTS_PER_PAGE: u32 = 10;
+
+///Клиент только для чтения, берущий всё из архива. Если чего-то в архиве
+///нет, возвращает `NumError(NotFound)`, как Табун на несуществующей странице
+#[cfg(feature = "archive")]
+pub struct OfflineClient {
+    archive: Archive,
+}
+
+#[cfg(feature = "archive")]
+impl OfflineClient {
+    ///Открывает архив, см. [`Archive::open`](../archive/struct.Archive.html#method.open)
+    pub fn open<P: AsRef<Path>>(path: P) -> TabunResult<OfflineClient> {
+        Ok(OfflineClient{ archive: try!(Archive::open(path)) })
+    }
+
+    pub fn archive(&self) -> &Archive {
+        &self.archive
+    }
+}
+
+#[cfg(feature = "archive")]
+impl OfflineClient {
+    ///Лежит ли пост в архиве, причём в блоге `blog_name`. Пустое имя блога,
+    ///как и в [`TClient::get_post`](../struct.TClient.html#method.get_post),
+    ///подходит к любому блогу
+    fn post_in_blog(&self, blog_name: &str, post_id: u32) -> TabunResult<bool> {
+        Ok(match try!(self.archive.post_blog(post_id)) {
+            Some(blog)  => blog_name.is_empty() || blog == blog_name,
+            None        => false
+        })
+    }
+}
+
+#[cfg(feature = "archive")]
+impl Reader for OfflineClient {
+    fn get_post(&mut self, blog_name: &str, post_id: u32) -> TabunResult<Post> {
+        if !try!(self.post_in_blog(blog_name, post_id)) {
+            return Err(TabunError::NumError(StatusCode::NotFound));
+        }
+
+        match try!(self.archive.load_post(post_id)) {
+            Some(x) => Ok(x),
+            None    => Err(TabunError::NumError(StatusCode::NotFound))
+        }
+    }
+
+    fn get_posts(&mut self, blog_name: &str, page: u32) -> TabunResult<Page<Post>> {
+        let page = if page == 0 { 1 } else { page };
+        let (posts, total) = try!(self.archive.load_posts(blog_name, (page - 1) * POSTS_PER_PAGE, POSTS_PER_PAGE));
+
+        let total_pages = if total == 0 { 1 } else { (total + POSTS_PER_PAGE - 1) / POSTS_PER_PAGE };
+        if page > total_pages {
+            return Err(TabunError::NumError(StatusCode::NotFound));
+        }
+
+        Ok(Page{
+            items:          posts,
+            page:           page,
+            total_pages:    total_pages,
+            next:           if page < total_pages { Some(format!("/blog/{}/page{}/", blog_name, page + 1)) } else { None },
+            prev:           if page > 1 { Some(format!("/blog/{}/page{}/", blog_name, page - 1)) } else { None },
+            total:          Some(total),
+        })
+    }
+
+    fn get_comments(&mut self, url: &str) -> TabunResult<HashMap<u32, Comment>> {
+        let post_id = try_to_parse!(hado!{
+            re <- Regex::new(r"(\d+)\.html").ok();
+            captures <- re.captures(url);
+            id <- captures.at(1);
+            id.parse::<u32>().ok()
+        }, "Cannot find post ID in URL");
+
+        if !try!(self.archive.has_post(post_id)) {
+            return Err(TabunError::NumError(StatusCode::NotFound));
+        }
+        self.archive.load_comments(post_id)
+    }
+
+    fn get_profile(&mut self, name: &str) -> TabunResult<UserInfo> {
+        match try!(self.archive.load_user(name)) {
+            Some(x) => Ok(x),
+            None    => Err(TabunError::NumError(StatusCode::NotFound))
+        }
+    }
+}
+
+///Откуда брать данные для [`open`](fn.open.html). Логин и пароль
+///передаются как в [`TClient::new`](../struct.TClient.html#method.new),
+///`None` - анонимно
+#[cfg(feature = "archive")]
+pub enum Source<'a, P: AsRef<Path>> {
+    ///Только Табун
+    Online(Option<&'a str>, Option<&'a str>),
+
+    ///Только архив по указанному пути
+    Offline(P),
+
+    ///Табун, а если он недоступен (ошибка сети или 5xx) - архив
+    Fallback(Option<&'a str>, Option<&'a str>, P),
+}
+
+///Создаёт клиент для чтения из выбранного источника
+///
+///# Examples
+///```no_run
+///use libtabun::reader::{self, Reader, Source};
+///
+///// Табун, а если он лежит - архив
+///let mut client = reader::open(Source::Fallback(None, None, "lighthouse.sqlite")).unwrap();
+///let posts = client.get_posts("lighthouse", 1).unwrap();
+///```
+#[cfg(feature = "archive")]
+pub fn open<'a, P: AsRef<Path>>(source: Source<'a, P>) -> TabunResult<Box<Reader + 'a>> {
+    match source {
+        Source::Online(login, pass)             => Ok(Box::new(try!(TClient::new(login, pass)))),
+        Source::Offline(path)                   => Ok(Box::new(try!(OfflineClient::open(path)))),
+        Source::Fallback(login, pass, path)     => match TClient::new(login, pass) {
+            Ok(x)                                       => Ok(Box::new(x)),
+            Err(TabunError::IoError(_))                 => Ok(Box::new(try!(OfflineClient::open(path)))),
+            Err(TabunError::NumError(ref x)) if x.is_server_error()
+                                                        => Ok(Box::new(try!(OfflineClient::open(path)))),
+            Err(x)                                      => Err(x)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "archive"))]
+mod test {
+    use super::*;
+    use ::TabunError;
+    use ::test::post;
+    use hyper::status::StatusCode;
+
+    #[test]
+    fn test_offline_posts() {
+        let mut client = OfflineClient::open(":memory:").unwrap();
+
+        for id in 1..13 {
+            let mut post = post(id);
+            post.title = format!("Пост {}", id);
+            client.archive.save_post("news", &post, &[]).unwrap();
+        }
+
+        let reader: &mut Reader = &mut client;
+
+        let first = reader.get_posts("news", 1).unwrap();
+        assert_eq!(first.items.len(), 10);
+        assert_eq!(first.items[0].id, 12);
+        assert_eq!((first.page, first.total_pages), (1, 2));
+        assert_eq!(first.next, Some("/blog/news/page2/".to_string()));
+
+        let second = reader.get_posts("news", 2).unwrap();
+        assert_eq!(second.items.iter().map(|x| x.id).collect::<Vec<_>>(), vec![2, 1]);
+        assert_eq!(second.next, None);
+
+        assert_eq!(reader.get_post("news", 5).unwrap().title, "Пост 5");
+        assert_eq!(reader.get_post("", 5).unwrap().title, "Пост 5");
+        assert!(match reader.get_post("fanart", 5) {
+            Err(TabunError::NumError(StatusCode::NotFound)) => true,
+            _                                               => false
+        });
+        assert!(reader.get_comments("/blog/news/5.html").unwrap().is_empty());
+        assert!(match reader.get_profile("Orhideous") {
+            Err(TabunError::NumError(StatusCode::NotFound)) => true,
+            _                                               => false
+        });
+    }
+}
@@ -0,0 +1,401 @@
+/* Rendering post and comment bodies to plain text and Markdown
+ *
+ * Copyright (C) 2016 TyanNN <TyanNN@cocaine.ninja>
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along
+ * with this program; if not, write to the Free Software Foundation, Inc.,
+ * 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+*/
+
+//! Перевод текстов постов и комментов в простой текст и Markdown.
+//!
+//! Markdown получается в том же диалекте, который понимает
+//! [`markdown::to_tabun`](../markdown/fn.to_tabun.html), так что
+//! текст можно перевести туда и обратно.
+//!
+//! ```
+//! use libtabun::{markup, render};
+//!
+//! let tree = markup::parse("<strong>Привет</strong>, <ls user=\"Orhideous\" />!");
+//! assert_eq!(render::to_text(&tree, false), "Привет, @Orhideous!");
+//! assert_eq!(render::to_markdown(&tree, false), "**Привет**, @Orhideous!");
+//! ```
+
+use super::{Post, Comment};
+
+use std::iter::repeat;
+
+use markup::{self, MarkupNode};
+
+///Переводит дерево в простой текст. Если `hide_spoilers`, то от спойлеров
+///остаются только заголовки
+pub fn to_text(nodes: &[MarkupNode], hide_spoilers: bool) -> String {
+    let renderer = Renderer{ markdown: false, hide_spoilers: hide_spoilers };
+    tidy(&renderer.render(nodes))
+}
+
+///Переводит дерево в Markdown. Если `hide_spoilers`, то от спойлеров
+///остаются только заголовки
+pub fn to_markdown(nodes: &[MarkupNode], hide_spoilers: bool) -> String {
+    let renderer = Renderer{ markdown: true, hide_spoilers: hide_spoilers };
+    tidy(&renderer.render(nodes))
+}
+
+impl Post {
+    ///Текст поста без разметки
+    pub fn body_text(&self, hide_spoilers: bool) -> String {
+        to_text(&markup::parse(&self.body), hide_spoilers)
+    }
+
+    ///Текст поста в Markdown
+    pub fn body_markdown(&self, hide_spoilers: bool) -> String {
+        to_markdown(&markup::parse(&self.body), hide_spoilers)
+    }
+}
+
+impl Comment {
+    ///Текст коммента без разметки
+    pub fn body_text(&self, hide_spoilers: bool) -> String {
+        to_text(&markup::parse(&self.body), hide_spoilers)
+    }
+
+    ///Текст коммента в Markdown
+    pub fn body_markdown(&self, hide_spoilers: bool) -> String {
+        to_markdown(&markup::parse(&self.body), hide_spoilers)
+    }
+}
+
+///Убирает пробелы в концах строк и лишние пустые строки
+fn tidy(text: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+
+    for line in text.lines().map(|x| x.trim_right()) {
+        if line.is_empty() && lines.last().map_or(true, |x| x.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+
+    while lines.last().map_or(false, |x| x.is_empty()) {
+        lines.pop();
+    }
+
+    lines.join("\n")
+}
+
+///Убирает пробелы в конце и начинает новый абзац
+fn paragraph(out: &mut String) {
+    let len = out.trim_right_matches(' ').len();
+    out.truncate(len);
+
+    if out.is_empty() {
+        return;
+    }
+    if !out.ends_with('\n') {
+        out.push('\n');
+    }
+    if !out.ends_with("\n\n") {
+        out.push('\n');
+    }
+}
+
+///Добавляет отдельный блок текста
+fn block(out: &mut String, text: &str) {
+    paragraph(out);
+    out.push_str(text);
+    out.push_str("\n\n");
+}
+
+///Добавляет префикс к первой строке и отступ такой же ширины к остальным
+fn prefix_lines(first: &str, text: &str) -> String {
+    let indent = repeat(' ').take(first.chars().count()).collect::<String>();
+
+    text.lines().enumerate()
+        .map(|(i, line)| {
+            if i == 0 {
+                format!("{}{}", first, line)
+            } else if line.is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", indent, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+///Весь текст из узлов как есть, без разметки и схлопывания пробелов
+fn raw_text(nodes: &[MarkupNode]) -> String {
+    let mut ret = String::new();
+    markup::walk(nodes, &mut |x| match *x {
+        MarkupNode::Text(ref t) => ret.push_str(t),
+        MarkupNode::LineBreak   => ret.push('\n'),
+        _                       => {}
+    });
+    ret
+}
+
+struct Renderer {
+    markdown:       bool,
+    hide_spoilers:  bool,
+}
+
+impl Renderer {
+    fn render(&self, nodes: &[MarkupNode]) -> String {
+        let mut out = String::new();
+        self.render_into(nodes, &mut out);
+        out
+    }
+
+    fn render_into(&self, nodes: &[MarkupNode], out: &mut String) {
+        let mut i = 0;
+
+        while i < nodes.len() {
+            if nodes[i] == MarkupNode::LineBreak {
+                // Несколько переносов подряд на Табуне означают новый абзац
+                let mut breaks = 0;
+                while i < nodes.len() {
+                    match nodes[i] {
+                        MarkupNode::LineBreak => breaks += 1,
+                        MarkupNode::Text(ref x) if x.trim().is_empty() => {},
+                        _ => break
+                    }
+                    i += 1;
+                }
+
+                if breaks > 1 {
+                    paragraph(out);
+                } else {
+                    self.line_break(out);
+                }
+                continue;
+            }
+
+            self.node(&nodes[i], out);
+            i += 1;
+        }
+    }
+
+    fn line_break(&self, out: &mut String) {
+        let len = out.trim_right_matches(' ').len();
+        out.truncate(len);
+
+        if out.is_empty() || out.ends_with('\n') {
+            return;
+        }
+        out.push_str(if self.markdown { "\\\n" } else { "\n" });
+    }
+
+    fn escape(&self, text: &str) -> String {
+        if !self.markdown {
+            return text.to_string();
+        }
+
+        let mut ret = String::with_capacity(text.len());
+        for c in text.chars() {
+            if "\\`*_[]<@~".contains(c) {
+                ret.push('\\');
+            }
+            ret.push(c);
+        }
+        ret
+    }
+
+    ///Добавляет текст, схлопывая пробелы и переносы так же, как браузер
+    fn push_text(&self, out: &mut String, text: &str) {
+        for c in text.chars() {
+            if c.is_whitespace() {
+                if !(out.is_empty() || out.ends_with(' ') || out.ends_with('\n')) {
+                    out.push(' ');
+                }
+            } else {
+                out.push_str(&self.escape(&c.to_string()));
+            }
+        }
+    }
+
+    ///Обрамляет выделенный текст, не захватывая пробелы по краям
+    fn wrap(&self, out: &mut String, children: &[MarkupNode], marker: &str) {
+        let inner = self.render(children);
+
+        if !self.markdown || inner.trim().is_empty() {
+            self.render_into(children, out);
+            return;
+        }
+
+        let raw = raw_text(children);
+        if raw.starts_with(char::is_whitespace) {
+            self.push_text(out, " ");
+        }
+
+        out.push_str(marker);
+        out.push_str(inner.trim());
+        out.push_str(marker);
+
+        if raw.ends_with(char::is_whitespace) {
+            out.push(' ');
+        }
+    }
+
+    fn cut(&self, out: &mut String, name: &Option<String>) {
+        // В простом тексте кат не нужен
+        if self.markdown {
+            block(out, &match *name {
+                Some(ref x) => format!("[cut {}]", x),
+                None        => "[cut]".to_string()
+            });
+        }
+    }
+
+    fn node(&self, node: &MarkupNode, out: &mut String) {
+        match *node {
+            MarkupNode::Text(ref x) => self.push_text(out, x),
+            MarkupNode::LineBreak => self.line_break(out),
+            MarkupNode::Cut(ref name) => self.cut(out, name),
+            MarkupNode::Spoiler{ref title, ref body} => {
+                let title = self.escape(title);
+
+                if self.hide_spoilers {
+                    block(out, &format!("[Спойлер: {}]", title));
+                } else if self.markdown {
+                    block(out, &format!(":::spoiler {}\n{}\n:::", title, tidy(&self.render(body))));
+                } else {
+                    block(out, &format!("[Спойлер: {}]\n{}", title, tidy(&self.render(body))));
+                }
+            },
+            MarkupNode::UserMention(ref x) => out.push_str(&format!("@{}", x)),
+            MarkupNode::Video(ref x) => {
+                block(out, &if self.markdown { format!("[видео]({})", x) } else { format!("[видео: {}]", x) });
+            },
+            MarkupNode::Image{ref src, ref alt, ..} => {
+                out.push_str(&if self.markdown {
+                    format!("![{}]({})", self.escape(alt), src)
+                } else if alt.is_empty() {
+                    format!("[картинка: {}]", src)
+                } else {
+                    format!("[картинка: {}]", alt)
+                });
+            },
+            MarkupNode::Link{ref href, ref children} => {
+                // Ссылка "читать дальше" в списке постов
+                if href.ends_with("#cut") {
+                    return self.cut(out, &None);
+                }
+
+                let plain = Renderer{ markdown: false, hide_spoilers: self.hide_spoilers }.render(children);
+                let plain = plain.trim();
+
+                if plain.is_empty() || plain == href {
+                    out.push_str(&if self.markdown { format!("<{}>", href) } else { href.clone() });
+                } else if self.markdown {
+                    out.push_str(&format!("[{}]({})", self.render(children).trim(), href));
+                } else {
+                    out.push_str(&format!("{} ({})", plain, href));
+                }
+            },
+            MarkupNode::Element{ref name, ref children, ..} => match name.as_str() {
+                "b" | "strong"              => self.wrap(out, children, "**"),
+                "i" | "em"                  => self.wrap(out, children, "*"),
+                "s" | "strike" | "del"      => self.wrap(out, children, "~~"),
+                "code" if self.markdown     => out.push_str(&format!("`{}`", raw_text(children))),
+                "pre" => {
+                    let code = raw_text(children);
+                    let code = code.trim_matches('\n');
+                    block(out, &if self.markdown { format!("```\n{}\n```", code) } else { code.to_string() });
+                },
+                "blockquote" => {
+                    let inner = tidy(&self.render(children));
+                    let quoted = inner.lines()
+                        .map(|x| if x.is_empty() { ">".to_string() } else { format!("> {}", x) })
+                        .collect::<Vec<_>>();
+                    block(out, &quoted.join("\n"));
+                },
+                "ul" | "ol" => {
+                    let ordered = name == "ol";
+                    let mut items = Vec::new();
+
+                    for child in children {
+                        if let MarkupNode::Element{ref name, ref children, ..} = *child {
+                            if name == "li" {
+                                let marker = if ordered { format!("{}. ", items.len() + 1) } else { "- ".to_string() };
+                                items.push(prefix_lines(&marker, &tidy(&self.render(children))));
+                            }
+                        }
+                    }
+
+                    block(out, &items.join("\n"));
+                },
+                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                    let level = match name.as_str() {
+                        "h1" | "h4" => 1,
+                        "h2" | "h5" => 2,
+                        _           => 3
+                    };
+
+                    let text = tidy(&self.render(children));
+                    block(out, &if self.markdown { format!("{} {}", repeat('#').take(level).collect::<String>(), text) } else { text });
+                },
+                "hr" => block(out, "---"),
+                "p" | "div" | "center" | "table" | "tr" | "li" => {
+                    block(out, &tidy(&self.render(children)));
+                },
+                "td" | "th" => {
+                    self.render_into(children, out);
+                    self.push_text(out, " ");
+                },
+                "script" | "style" => {},
+                _ => self.render_into(children, out)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use markup;
+
+    fn text(body: &str, hide_spoilers: bool) -> String {
+        to_text(&markup::parse(body), hide_spoilers)
+    }
+
+    fn md(body: &str) -> String {
+        to_markdown(&markup::parse(body), false)
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(text("Привет, <ls user=\"Orhideous\" />!<br/>\nНовая строка<br/><br/>Абзац", false),
+                   "Привет, @Orhideous!\nНовая строка\n\nАбзац");
+        assert_eq!(text("a &amp; b &quot;c&quot; <a href=\"http://x.ru\">сайт</a>", false),
+                   "a & b \"c\" сайт (http://x.ru)");
+    }
+
+    #[test]
+    fn test_spoilers() {
+        let body = r#"<div class="spoiler"><div class="spoiler-title">Тайна</div><div class="spoiler-body">Текст</div></div>"#;
+        assert_eq!(text(body, true), "[Спойлер: Тайна]");
+        assert_eq!(text(body, false), "[Спойлер: Тайна]\nТекст");
+        assert_eq!(md(body), ":::spoiler Тайна\nТекст\n:::");
+    }
+
+    #[test]
+    fn test_markdown() {
+        assert_eq!(md(r#"<strong>жирный</strong> <a href="http://x.ru">ссылка</a> <img src="/1.png" alt="пони">"#),
+                   "**жирный** [ссылка](http://x.ru) ![пони](/1.png)");
+        assert_eq!(md("<ul><li>раз</li><li>два</li></ul><blockquote>цитата</blockquote>"),
+                   "- раз\n- два\n\n> цитата");
+        assert_eq!(md("<ol><li>раз</li></ol><h4>Заголовок</h4>"), "1. раз\n\n# Заголовок");
+        assert_eq!(md("2*3_4 <cut>"), "2\\*3\\_4\n\n[cut]");
+    }
+}
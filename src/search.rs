@@ -0,0 +1,126 @@
+/* Tags and search
+ *
+ * Copyright (C) 2016 TyanNN <TyanNN@cocaine.ninja>
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along
+ * with this program; if not, write to the Free Software Foundation, Inc.,
+ * 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+*/
+
+extern crate select;
+extern crate regex;
+extern crate url;
+
+use super::*;
+
+use select::document::Document;
+use select::predicate::{Name,Class,And};
+
+use regex::Regex;
+
+use url::form_urlencoded::byte_serialize;
+use url::percent_encoding::{utf8_percent_encode, PATH_SEGMENT_ENCODE_SET};
+
+impl<'a> TClient<'a> {
+
+    ///Получает посты с указанным тэгом
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let posts = user.get_tag_posts("успех", 1).unwrap();
+    ///println!("Страница {} из {}", posts.page, posts.total_pages);
+    ///```
+    pub fn get_tag_posts(&mut self, tag: &str, page: u32) -> TabunResult<Page<Post>> {
+        let url = format!("/tag/{}/page{}/", utf8_percent_encode(tag, PATH_SEGMENT_ENCODE_SET), page);
+        let doc = try!(self.get_document(&url));
+        let posts = try!(self.doc_get_posts(&doc));
+        Ok(self.doc_get_page(&doc, posts))
+    }
+
+    ///Ищет посты
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let found = user.search_posts("libtabun", 1).unwrap();
+    ///println!("Найдено постов: {:?}", found.total);
+    ///for post in &found.items {
+    ///    println!("{} - {}", post.id, post.title);
+    ///}
+    ///```
+    pub fn search_posts(&mut self, query: &str, page: u32) -> TabunResult<Page<Post>> {
+        let url = format!("/search/topics/page{}/?q={}", page, byte_serialize(query.as_bytes()).collect::<String>());
+        let doc = try!(self.get_document(&url));
+
+        let posts = try!(self.doc_get_posts(&doc));
+        let mut ret = self.doc_get_page(&doc, posts);
+        ret.total = self.doc_get_search_total(&doc);
+        Ok(ret)
+    }
+
+    ///Ищет комментарии, найденное возвращается
+    ///от новых к старым
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///user.search_comments("libtabun", 1);
+    ///```
+    pub fn search_comments(&mut self, query: &str, page: u32) -> TabunResult<Page<Comment>> {
+        let url = format!("/search/comments/page{}/?q={}", page, byte_serialize(query.as_bytes()).collect::<String>());
+        let doc = try!(self.get_document(&url));
+
+        let mut comments = try!(self.doc_get_comments(&doc, None)).into_iter()
+            .map(|(_, x)| x)
+            .collect::<Vec<_>>();
+        comments.sort_by(|a, b| b.id.cmp(&a.id));
+
+        let mut ret = self.doc_get_page(&doc, comments);
+        ret.total = self.doc_get_search_total(&doc);
+        Ok(ret)
+    }
+
+    ///Получает общее кол-во найденного из активной вкладки
+    ///на странице результатов поиска
+    pub fn doc_get_search_total(&self, doc: &Document) -> Option<u32> {
+        let count_regex = Regex::new(r"^\s*(\d+)").unwrap();
+
+        hado!{
+            tab <- doc.find(And(Name("ul"),Class("nav-pills"))).find(And(Name("li"),Class("active"))).first();
+            text <- Some(tab.text());
+            captures <- count_regex.captures(&text);
+            at <- captures.at(1);
+            at.parse::<u32>().ok()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::TClient;
+
+    #[test]
+    fn test_get_tag_posts() {
+        let mut user = TClient::new(None,None).unwrap();
+        match user.get_tag_posts("успех", 1) {
+            Ok(x)   => {
+                assert!(!x.items.is_empty());
+                for post in &x.items {
+                    assert!(post.tags.contains(&"успех".to_string()));
+                }
+            },
+            Err(x)  => panic!(x)
+        }
+    }
+}
@@ -0,0 +1,286 @@
+/* Profile and account settings
+ *
+ * Copyright (C) 2016 TyanNN <TyanNN@cocaine.ninja>
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along
+ * with this program; if not, write to the Free Software Foundation, Inc.,
+ * 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+*/
+
+extern crate select;
+
+use super::*;
+
+use select::document::Document;
+use select::predicate::{Name,Attr,And};
+
+use std::io::Read;
+
+///Возвращает значение выбранного пункта в `<select>` с указанным именем
+fn selected_option(doc: &Document, name: &str) -> Option<String> {
+    let select = match doc.find(And(Name("select"),Attr("name",name))).first() {
+        Some(x) => x,
+        None    => return None
+    };
+
+    select.find(Name("option")).iter()
+        .find(|x| x.attr("selected").is_some())
+        .and_then(|x| x.attr("value"))
+        .map(|x| x.to_string())
+}
+
+///Возвращает, отмечен ли чекбокс с указанным ID
+fn is_checked(doc: &Document, id: &str) -> bool {
+    match doc.find(And(Name("input"),Attr("id",id))).first() {
+        Some(x) => x.attr("checked").is_some(),
+        None    => false
+    }
+}
+
+impl<'a> TClient<'a> {
+
+    ///Получает настройки профиля текущего пользователя
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let mut settings = user.get_profile_settings().unwrap();
+    ///settings.about = "Люблю пони".to_string();
+    ///user.set_profile_settings(&settings).unwrap();
+    ///```
+    pub fn get_profile_settings(&mut self) -> TabunResult<ProfileSettings> {
+        let doc = try!(self.get_document("/settings/profile/"));
+        self.doc_get_profile_settings(&doc)
+    }
+
+    pub fn doc_get_profile_settings(&self, doc: &Document) -> TabunResult<ProfileSettings> {
+        let realname = try_to_parse!(doc.find(And(Name("input"),Attr("name","profile_name"))).first());
+        let realname = realname.attr("value").unwrap_or("").to_string();
+
+        let about = try_to_parse!(doc.find(And(Name("textarea"),Attr("name","profile_about"))).first()).text();
+
+        let gender = match selected_option(doc, "profile_sex") {
+            Some(ref x) if x == "man"   => Gender::Male,
+            Some(ref x) if x == "woman" => Gender::Female,
+            _                           => Gender::Other
+        };
+
+        let birthday = hado!{
+            day <- selected_option(doc, "profile_birthday_day");
+            day <- day.parse::<u32>().ok();
+            month <- selected_option(doc, "profile_birthday_month");
+            month <- month.parse::<u32>().ok();
+            year <- selected_option(doc, "profile_birthday_year");
+            year <- year.parse::<u32>().ok();
+            Some((day,month,year))
+        };
+
+        let types = doc.find(And(Name("select"),Attr("name","profile_user_field_type[]")));
+        let values = doc.find(And(Name("input"),Attr("name","profile_user_field_value[]")));
+
+        let contacts = types.iter().zip(values.iter())
+            .filter_map(|(t, v)| {
+                let typ = hado!{
+                    opt <- t.find(Name("option")).iter().find(|x| x.attr("selected").is_some());
+                    opt.attr("value")
+                };
+                match (typ, v.attr("value")) {
+                    (Some(t), Some(v)) => Some((t.to_string(), v.to_string())),
+                    _                  => None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Ok(ProfileSettings{
+            realname:   realname,
+            about:      about,
+            gender:     gender,
+            birthday:   birthday,
+            country:    selected_option(doc, "geo_country").unwrap_or_else(String::new),
+            region:     selected_option(doc, "geo_region").unwrap_or_else(String::new),
+            city:       selected_option(doc, "geo_city").unwrap_or_else(String::new),
+            contacts:   contacts,
+        })
+    }
+
+    ///Сохраняет настройки профиля текущего пользователя. Табун сохраняет
+    ///форму целиком, поэтому лучше сначала получить текущие настройки через
+    ///[`get_profile_settings`](#method.get_profile_settings) и изменить в них
+    ///только нужное.
+    pub fn set_profile_settings(&mut self, settings: &ProfileSettings) -> TabunResult<()> {
+        let key = self.security_ls_key.to_owned();
+
+        let gender = match settings.gender {
+            Gender::Male   => "man",
+            Gender::Female => "woman",
+            Gender::Other  => "other"
+        };
+
+        let (day, month, year) = match settings.birthday {
+            Some((d, m, y)) => (d.to_string(), m.to_string(), y.to_string()),
+            None            => (String::new(), String::new(), String::new())
+        };
+
+        let mut bd = vec![
+            ("profile_name",            settings.realname.as_str()),
+            ("profile_sex",             gender),
+            ("profile_birthday_day",    day.as_str()),
+            ("profile_birthday_month",  month.as_str()),
+            ("profile_birthday_year",   year.as_str()),
+            ("geo_country",             settings.country.as_str()),
+            ("geo_region",              settings.region.as_str()),
+            ("geo_city",                settings.city.as_str()),
+            ("profile_about",           settings.about.as_str()),
+            ("submit_profile_edit",     "Сохранить"),
+            ("security_ls_key",         &key)
+        ];
+
+        for &(ref typ, ref value) in &settings.contacts {
+            bd.push(("profile_user_field_type[]", typ.as_str()));
+            bd.push(("profile_user_field_value[]", value.as_str()));
+        }
+
+        try!(self.post_multipart("/settings/profile/", &bd));
+        Ok(())
+    }
+
+    ///Получает настройки уведомлений на почту
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let mut notices = user.get_notice_settings().unwrap();
+    ///notices.new_comment = false;
+    ///user.set_notice_settings(&notices).unwrap();
+    ///```
+    pub fn get_notice_settings(&mut self) -> TabunResult<NoticeSettings> {
+        let doc = try!(self.get_document("/settings/tuning/"));
+        self.doc_get_notice_settings(&doc)
+    }
+
+    pub fn doc_get_notice_settings(&self, doc: &Document) -> TabunResult<NoticeSettings> {
+        // Проверяем, что это вообще страница настроек
+        try_to_parse!(doc.find(Attr("name","submit_settings_tuning")).first());
+
+        Ok(NoticeSettings{
+            new_topic:      is_checked(doc, "settings_notice_new_topic"),
+            new_comment:    is_checked(doc, "settings_notice_new_comment"),
+            new_talk:       is_checked(doc, "settings_notice_new_talk"),
+            reply_comment:  is_checked(doc, "settings_notice_reply_comment"),
+            new_friend:     is_checked(doc, "settings_notice_new_friend"),
+        })
+    }
+
+    ///Сохраняет настройки уведомлений на почту
+    pub fn set_notice_settings(&mut self, settings: &NoticeSettings) -> TabunResult<()> {
+        let key = self.security_ls_key.to_owned();
+
+        let mut bd = vec![
+            ("submit_settings_tuning",  "Сохранить"),
+            ("security_ls_key",         &key)
+        ];
+
+        let notices = vec![
+            ("settings_notice_new_topic",       settings.new_topic),
+            ("settings_notice_new_comment",     settings.new_comment),
+            ("settings_notice_new_talk",        settings.new_talk),
+            ("settings_notice_reply_comment",   settings.reply_comment),
+            ("settings_notice_new_friend",      settings.new_friend)
+        ];
+
+        // Неотмеченные чекбоксы браузер просто не отправляет
+        for (name, enabled) in notices {
+            if enabled {
+                bd.push((name, "1"));
+            }
+        }
+
+        try!(self.post_multipart("/settings/tuning/", &bd));
+        Ok(())
+    }
+
+    ///Загружает и применяет аватар или фото профиля, `kind` - `avatar` или `foto`.
+    ///Возвращает ссылку на новую картинку
+    fn upload_profile_image(&mut self, kind: &str, value: MultipartValue) -> TabunResult<String> {
+        let mut bd = vec![(kind, value)];
+
+        let data = try!(self.ajax_with_files(
+            &format!("/settings/profile/upload-{}/", kind),
+            &mut bd
+        ));
+
+        if get_json!(data, "/sTmpFile", as_str).is_none() {
+            return Err(parse_error!("Server did not return sTmpFile"))
+        }
+
+        // Без указания размеров Табун берёт картинку целиком
+        let data = try!(self.ajax(&format!("/settings/profile/resize-{}/", kind), &[]));
+
+        match get_json!(data, "/sFile", as_str) {
+            Some(x) => Ok(x.to_string()),
+            None => Err(parse_error!("Server did not return sFile"))
+        }
+    }
+
+    ///Удаляет аватар или фото профиля, возвращает ссылку на картинку по умолчанию
+    fn remove_profile_image(&mut self, kind: &str) -> TabunResult<String> {
+        let data = try!(self.ajax(&format!("/settings/profile/remove-{}/", kind), &[]));
+
+        match get_json!(data, "/sFile", as_str) {
+            Some(x) => Ok(x.to_string()),
+            None => Err(parse_error!("Server did not return sFile"))
+        }
+    }
+
+    ///Загружает аватар из файла по указанному пути и возвращает ссылку на него
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let link = user.upload_avatar_from_file("images/tabunyasha.png").unwrap();
+    ///```
+    pub fn upload_avatar_from_file(&mut self, path: &str) -> TabunResult<String> {
+        self.upload_profile_image("avatar", MultipartValue::File(path))
+    }
+
+    ///Загружает аватар из указанного потока и возвращает ссылку на него
+    pub fn upload_avatar_from_stream(&mut self, filename: &str, stream: &mut Read) -> TabunResult<String> {
+        self.upload_profile_image("avatar", MultipartValue::Stream(filename, stream))
+    }
+
+    ///Удаляет аватар, возвращает ссылку на аватар по умолчанию
+    pub fn remove_avatar(&mut self) -> TabunResult<String> {
+        self.remove_profile_image("avatar")
+    }
+
+    ///Загружает фото профиля из файла по указанному пути и возвращает ссылку на него
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let link = user.upload_photo_from_file("images/tabunyasha.png").unwrap();
+    ///```
+    pub fn upload_photo_from_file(&mut self, path: &str) -> TabunResult<String> {
+        self.upload_profile_image("foto", MultipartValue::File(path))
+    }
+
+    ///Загружает фото профиля из указанного потока и возвращает ссылку на него
+    pub fn upload_photo_from_stream(&mut self, filename: &str, stream: &mut Read) -> TabunResult<String> {
+        self.upload_profile_image("foto", MultipartValue::Stream(filename, stream))
+    }
+
+    ///Удаляет фото профиля, возвращает ссылку на картинку по умолчанию
+    pub fn remove_photo(&mut self) -> TabunResult<String> {
+        self.remove_profile_image("foto")
+    }
+}
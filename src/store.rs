@@ -0,0 +1,275 @@
+/* Content-addressed local media store
+ *
+ * Copyright (C) 2016 TyanNN <TyanNN@cocaine.ninja>
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along
+ * with this program; if not, write to the Free Software Foundation, Inc.,
+ * 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+*/
+
+//! Локальное хранилище картинок.
+//!
+//! Файлы складываются по SHA-1 содержимого: `корень/ab/abcdef....png`,
+//! так что одна и та же картинка по разным ссылкам хранится один раз.
+//! Ключ - только хэш, расширение берётся от первой сохранённой копии.
+//!
+//! ```no_run
+//! # let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+//! let mut store = libtabun::store::MediaStore::new("archive/media").unwrap();
+//!
+//! let mut post = user.get_post("computers", 157198).unwrap();
+//! store.localize_post(&mut post);
+//! // Теперь картинки в post.body ведут в archive/media
+//! ```
+
+extern crate sha1;
+extern crate hyper;
+
+use super::{TabunResult, TabunError, Post, Comment, UserInfo, HOST_URL};
+
+use markup::{self, MarkupNode};
+use media::resolve_url;
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use hyper::client::Client;
+
+///Хранилище картинок в локальной папке
+pub struct MediaStore {
+    root:   PathBuf,
+    client: Client,
+
+    ///Уже скачанные ссылки и куда они легли
+    known:  HashMap<String, PathBuf>,
+
+    ///Ссылки, которые не удалось скачать
+    failed: Vec<String>,
+}
+
+///Приводит расширение к одному виду: `JPEG`, `jpeg` и `jpg` дают `jpg`
+fn normalize_extension(ext: &str) -> Option<String> {
+    let ext = ext.trim().to_lowercase();
+
+    match ext.as_str() {
+        "jpeg" | "jpe"  => Some("jpg".to_string()),
+        "svg+xml"       => Some("svg".to_string()),
+        "x-icon" | "vnd.microsoft.icon" => Some("ico".to_string()),
+        x if !x.is_empty() && x.len() <= 5 && x.chars().all(|c| c.is_alphanumeric()) => Some(x.to_string()),
+        _               => None
+    }
+}
+
+///Расширение файла по Content-Type, а если его нет, то по ссылке
+fn extension(url: &str, content_type: Option<&str>) -> String {
+    let from_type = content_type
+        .and_then(|x| x.split(';').next())
+        .and_then(|x| x.split('/').nth(1))
+        .and_then(normalize_extension);
+
+    if let Some(x) = from_type {
+        return x;
+    }
+
+    let path = url.split(|c| c == '?' || c == '#').next().unwrap_or("");
+    let name = path.rsplit('/').next().unwrap_or("");
+
+    name.rfind('.')
+        .and_then(|pos| normalize_extension(&name[pos + 1..]))
+        .unwrap_or_else(|| "bin".to_string())
+}
+
+impl MediaStore {
+    ///Открывает хранилище в указанной папке, создавая её при необходимости
+    pub fn new<P: AsRef<Path>>(root: P) -> TabunResult<MediaStore> {
+        try!(fs::create_dir_all(root.as_ref()));
+
+        Ok(MediaStore{
+            root:   root.as_ref().to_path_buf(),
+            client: Client::new(),
+            known:  HashMap::new(),
+            failed: Vec::new(),
+        })
+    }
+
+    ///Ссылки, которые не удалось скачать. В текстах они остаются как были
+    pub fn failed(&self) -> &[String] {
+        &self.failed
+    }
+
+    ///Ищет уже сохранённый файл с таким хэшем, с любым расширением
+    fn find_stored(dir: &Path, hash: &str) -> TabunResult<Option<PathBuf>> {
+        for entry in try!(fs::read_dir(dir)) {
+            let path = try!(entry).path();
+            // У недописанных файлов `hash.ext.part` стем другой
+            if path.file_stem().map_or(false, |x| x == hash) {
+                return Ok(Some(path));
+            }
+        }
+        Ok(None)
+    }
+
+    ///Кладёт данные в хранилище и возвращает путь к файлу. Если такие
+    ///данные уже есть, то ничего не пишет и возвращает путь к ним, даже
+    ///если они были сохранены с другим расширением
+    pub fn store(&self, data: &[u8], ext: &str) -> TabunResult<PathBuf> {
+        let mut hasher = sha1::Sha1::new();
+        hasher.update(data);
+        let hash = hasher.digest().to_string();
+
+        let dir = self.root.join(&hash[..2]);
+        try!(fs::create_dir_all(&dir));
+
+        if let Some(x) = try!(MediaStore::find_stored(&dir, &hash)) {
+            return Ok(x);
+        }
+
+        let ext = normalize_extension(ext).unwrap_or_else(|| "bin".to_string());
+        let path = dir.join(format!("{}.{}", hash, ext));
+
+        // Пишем во временный файл, чтобы не оставить недописанный при падении
+        let tmp = dir.join(format!("{}.{}.part", hash, ext));
+        {
+            let mut file = try!(File::create(&tmp));
+            try!(file.write_all(data));
+        }
+        try!(fs::rename(&tmp, &path));
+
+        Ok(path)
+    }
+
+    ///Скачивает файл по абсолютной ссылке и возвращает путь к нему
+    pub fn download(&mut self, url: &str) -> TabunResult<PathBuf> {
+        if let Some(x) = self.known.get(url) {
+            return Ok(x.clone());
+        }
+
+        let mut res = try!(self.client.get(url).send());
+        if res.status != hyper::Ok {
+            return Err(TabunError::from(res.status));
+        }
+
+        let mut data = Vec::new();
+        try!(res.read_to_end(&mut data));
+
+        let content_type = res.headers.get_raw("content-type")
+            .and_then(|x| x.get(0))
+            .map(|x| String::from_utf8_lossy(x).into_owned());
+
+        let path = try!(self.store(&data, &extension(url, content_type.as_ref().map(|x| x.as_str()))));
+        self.known.insert(url.to_string(), path.clone());
+        Ok(path)
+    }
+
+    ///Скачивает картинку по ссылке из текста (относительной или без протокола),
+    ///при ошибке запоминает ссылку в `failed` и возвращает None
+    fn localize_url(&mut self, src: &str) -> Option<String> {
+        let url = match resolve_url(HOST_URL, src) {
+            Some(x) => x,
+            None    => return None
+        };
+
+        if self.failed.contains(&url) {
+            return None;
+        }
+
+        match self.download(&url) {
+            Ok(x)   => Some(x.to_string_lossy().into_owned()),
+            Err(_)  => {
+                self.failed.push(url);
+                None
+            }
+        }
+    }
+
+    ///Скачивает картинки из текста и возвращает текст, в котором они
+    ///ведут на локальные файлы. Остальная разметка не трогается
+    pub fn localize_body(&mut self, body: &str) -> String {
+        let mut srcs = Vec::new();
+        markup::walk(&markup::parse(body), &mut |x| if let MarkupNode::Image{ref src, ..} = *x {
+            if !srcs.contains(src) {
+                srcs.push(src.clone());
+            }
+        });
+
+        let mut ret = body.to_string();
+        for src in srcs {
+            if let Some(local) = self.localize_url(&src) {
+                // В тексте амперсанды в ссылках могут быть как есть, так и &amp;
+                for form in &[src.clone(), src.replace('&', "&amp;")] {
+                    ret = ret.replace(&format!("src=\"{}\"", form), &format!("src=\"{}\"", local));
+                }
+            }
+        }
+        ret
+    }
+
+    ///Скачивает картинки из текста поста и фотографии фотосета
+    pub fn localize_post(&mut self, post: &mut Post) {
+        post.body = self.localize_body(&post.body);
+
+        for photo in &mut post.photos {
+            if let Some(local) = self.localize_url(&photo.url) {
+                photo.url = local;
+            }
+        }
+    }
+
+    ///Скачивает картинки из текста коммента
+    pub fn localize_comment(&mut self, comment: &mut Comment) {
+        comment.body = self.localize_body(&comment.body);
+    }
+
+    ///Скачивает аватар пользователя
+    pub fn localize_profile(&mut self, user: &mut UserInfo) {
+        if let Some(local) = self.localize_url(&user.userpic) {
+            user.userpic = local;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::extension;
+
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn test_extension() {
+        assert_eq!(extension("https://cdn.everypony.ru/a/1.PNG?v=2", None), "png");
+        assert_eq!(extension("https://cdn.everypony.ru/avatar", Some("image/jpeg; charset=binary")), "jpg");
+        assert_eq!(extension("https://cdn.everypony.ru/avatar", None), "bin");
+        assert_eq!(extension("https://cdn.everypony.ru/a/1.jpeg", None), "jpg");
+        assert_eq!(extension("https://cdn.everypony.ru/a/1.png", Some("image/jpeg")), "jpg");
+    }
+
+    #[test]
+    fn test_store_dedup() {
+        let root = env::temp_dir().join("libtabun-store-test");
+        let _ = fs::remove_dir_all(&root);
+
+        let store = MediaStore::new(&root).unwrap();
+        let first = store.store(b"pony", "png").unwrap();
+        let second = store.store(b"pony", "JPEG").unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(first, root.join("26").join("2629fb6d2384da89796a4811ef6db5f2ac657bab.png"));
+        assert_eq!(fs::read_dir(root.join("26")).unwrap().count(), 1);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
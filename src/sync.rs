@@ -0,0 +1,411 @@
+/* Incremental blog sync
+ *
+ * Copyright (C) 2016 TyanNN <TyanNN@cocaine.ninja>
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along
+ * with this program; if not, write to the Free Software Foundation, Inc.,
+ * 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+*/
+
+//! Инкрементальная синхронизация блога с [архивом](../archive/index.html),
+//! включается фичей `archive`.
+//!
+//! Для каждого поста запоминаются хэши текста в списке и полного текста и
+//! кол-во комментов, для каждого коммента - хэш текста и флаг удаления. При
+//! очередном запуске просматриваются только первые страницы блога, пока на
+//! них есть новые посты, а страница поста загружается лишь для постов, у
+//! которых поменялся текст в списке или кол-во комментов. Найденные изменения
+//! сохраняются в архив и возвращаются как события.
+//!
+//! ```no_run
+//! # let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+//! use libtabun::sync::SyncEvent;
+//!
+//! let mut archive = libtabun::archive::Archive::open("lighthouse.sqlite").unwrap();
+//! for event in archive.sync_blog(&mut user, "lighthouse", 5).unwrap() {
+//!     match event {
+//!         SyncEvent::NewPost(post)    => println!("Новый пост: {}", post.title),
+//!         SyncEvent::DeletedPost(id)  => println!("Удалён пост {}", id),
+//!         _                           => {}
+//!     }
+//! }
+//! ```
+//!
+//! При первом запуске все просмотренные посты и комменты считаются новыми.
+//! Всё найденное за запуск пишется в архив одной транзакцией вместе с
+//! состоянием: если синхронизация прервалась ошибкой, архив не меняется, и
+//! следующий запуск найдёт те же изменения.
+//!
+//! # Ограничения
+//!
+//! По списку постов не видно правок текста под катом, а также правок и
+//! удалений комментов, если кол-во комментов при этом не изменилось (например,
+//! один удалили, другой добавили). Такие изменения находятся только
+//! перепроверкой: [`Archive::sync_blog_ext`](../archive/struct.Archive.html#method.sync_blog_ext)
+//! с `recheck` загружает страницу каждого просмотренного поста, по одному
+//! запросу на пост. Удалёнными считаются только те запомненные посты, что
+//! опубликованы позже самого старого из просмотренных, но пропали со
+//! страниц; про более старые посты один запуск ничего не знает.
+//!
+//! # Схема
+//!
+//! - `sync_blogs` - `blog`, `last_post_id`
+//! - `sync_posts` - `post_id`, `blog`, `list_hash` (текст в списке),
+//!   `body_hash` (полный текст), `comments_count`
+//! - `sync_comments` - `id`, `post_id`, `body_hash`, `deleted`
+//!
+//! Хэши - SHA-1 в hex.
+
+extern crate rusqlite;
+extern crate sha1;
+
+use super::{TClient, TabunResult, TabunError, Post, Comment};
+
+use archive::Archive;
+
+use std::cmp::max;
+use std::collections::HashMap;
+
+///SQL, создающий таблицы состояния синхронизации, если их ещё нет
+pub const SYNC_SCHEMA: &'static str = "
+CREATE TABLE IF NOT EXISTS sync_blogs (
+    blog            TEXT PRIMARY KEY,
+    last_post_id    INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS sync_posts (
+    post_id         INTEGER PRIMARY KEY,
+    blog            TEXT NOT NULL,
+    list_hash       TEXT NOT NULL,
+    body_hash       TEXT NOT NULL,
+    comments_count  INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS sync_comments (
+    id              INTEGER PRIMARY KEY,
+    post_id         INTEGER NOT NULL,
+    body_hash       TEXT NOT NULL,
+    deleted         INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS sync_comments_post ON sync_comments (post_id);
+";
+
+///Изменение, найденное при синхронизации
+#[derive(Debug,Clone)]
+pub enum SyncEvent {
+    NewPost(Post),
+
+    ///Полный текст поста изменился
+    EditedPost(Post),
+
+    ///Пост пропал из блога: удалён, перенесён в черновики или в другой блог.
+    ///В архиве он помечается через
+    ///[`set_post_deleted`](../archive/struct.Archive.html#method.set_post_deleted)
+    DeletedPost(u32),
+
+    NewComment(Comment),
+    EditedComment(Comment),
+
+    ///Коммент удалён: помечен удалённым (`Comment.deleted`) или пропал со страницы
+    DeletedComment{
+        post_id:    u32,
+        id:         u32
+    }
+}
+
+///Что известно о посте с прошлой синхронизации
+struct PostState {
+    list_hash:          String,
+    body_hash:          String,
+    comments_count:     u32,
+}
+
+fn hash(text: &str) -> String {
+    let mut hasher = sha1::Sha1::new();
+    hasher.update(text.as_bytes());
+    hasher.digest().to_string()
+}
+
+impl Archive {
+    fn post_state(&self, post_id: u32) -> TabunResult<Option<PostState>> {
+        match self.connection().query_row(
+            "SELECT list_hash, body_hash, comments_count FROM sync_posts WHERE post_id = ?",
+            &[&(post_id as i64)],
+            |row| PostState{
+                list_hash:          row.get(0),
+                body_hash:          row.get(1),
+                comments_count:     row.get::<_, i64>(2) as u32,
+            }) {
+            Ok(x)                                       => Ok(Some(x)),
+            Err(rusqlite::Error::QueryReturnedNoRows)   => Ok(None),
+            Err(x)                                      => Err(TabunError::from(x))
+        }
+    }
+
+    fn set_post_state(&self, blog: &str, post_id: u32, state: &PostState) -> TabunResult<()> {
+        try!(self.connection().execute(
+            "INSERT OR REPLACE INTO sync_posts
+             (post_id, blog, list_hash, body_hash, comments_count)
+             VALUES (?, ?, ?, ?, ?)",
+            &[&(post_id as i64), &blog, &state.list_hash, &state.body_hash,
+              &(state.comments_count as i64)]));
+        Ok(())
+    }
+
+    ///Посты блога из состояния, которые должны были попасть на просмотренные
+    ///страницы, но их там нет. Страницы идут подряд с первой, так что на них
+    ///есть каждый пост, опубликованный позже самого старого из просмотренных
+    ///(`oldest_date`). По ID так судить нельзя: черновик, опубликованный
+    ///поздно, оказывается на первой странице с маленьким ID
+    fn gone_posts(&self, blog: &str, seen: &[u32], oldest_date: &str) -> TabunResult<Vec<u32>> {
+        let mut stmt = try!(self.connection().prepare(
+            "SELECT s.post_id FROM sync_posts s JOIN posts p ON p.id = s.post_id
+             WHERE s.blog = ? AND p.date > ?"));
+        let rows = try!(stmt.query_map(&[&blog, &oldest_date], |row| row.get::<_, i64>(0) as u32));
+
+        let mut gone = Vec::new();
+        for row in rows {
+            let id = try!(row);
+            if !seen.contains(&id) {
+                gone.push(id);
+            }
+        }
+        Ok(gone)
+    }
+
+    fn last_post_id(&self, blog: &str) -> TabunResult<u32> {
+        match self.connection().query_row("SELECT last_post_id FROM sync_blogs WHERE blog = ?",
+                                          &[&blog], |row| row.get::<_, i64>(0)) {
+            Ok(x)                                       => Ok(x as u32),
+            Err(rusqlite::Error::QueryReturnedNoRows)   => Ok(0),
+            Err(x)                                      => Err(TabunError::from(x))
+        }
+    }
+
+    ///Сравнивает комменты поста с запомненными и сохраняет их
+    fn sync_comments(&mut self, post_id: u32, comments: &[Comment], events: &mut Vec<SyncEvent>) -> TabunResult<()> {
+        let mut known = HashMap::new();
+        {
+            let mut stmt = try!(self.connection().prepare(
+                "SELECT id, body_hash, deleted FROM sync_comments WHERE post_id = ?"));
+            let rows = try!(stmt.query_map(&[&(post_id as i64)], |row| {
+                (row.get::<_, i64>(0) as u32, row.get::<_, String>(1), row.get::<_, bool>(2))
+            }));
+            for row in rows {
+                let (id, body_hash, deleted) = try!(row);
+                known.insert(id, (body_hash, deleted));
+            }
+        }
+
+        for comment in comments {
+            let body_hash = hash(&comment.body);
+
+            match known.remove(&comment.id) {
+                None if comment.deleted                         => {},
+                None                                            => events.push(SyncEvent::NewComment(comment.clone())),
+                Some((_, false)) if comment.deleted             => events.push(SyncEvent::DeletedComment{
+                    post_id:    post_id,
+                    id:         comment.id
+                }),
+                Some((ref old, _)) if !comment.deleted && *old != body_hash => events.push(SyncEvent::EditedComment(comment.clone())),
+                Some(_)                                         => {}
+            }
+
+            try!(self.connection().execute(
+                "INSERT OR REPLACE INTO sync_comments (id, post_id, body_hash, deleted) VALUES (?, ?, ?, ?)",
+                &[&(comment.id as i64), &(post_id as i64), &body_hash, &comment.deleted]));
+        }
+
+        // Оставшиеся пропали со страницы совсем
+        for (id, (_, deleted)) in known {
+            if !deleted {
+                events.push(SyncEvent::DeletedComment{
+                    post_id:    post_id,
+                    id:         id
+                });
+            }
+            try!(self.connection().execute("UPDATE sync_comments SET deleted = 1 WHERE id = ?", &[&(id as i64)]));
+        }
+
+        self.save_comments(comments)
+    }
+
+    ///Находит изменения в блоге с прошлой синхронизации, сохраняет их в
+    ///архив и возвращает списком событий. Просматривает страницы блога,
+    ///пока на них есть новые посты, но не больше `max_pages`. То же, что
+    ///[`sync_blog_ext`](#method.sync_blog_ext) без перепроверки
+    pub fn sync_blog(&mut self, client: &mut TClient, blog: &str, max_pages: u32) -> TabunResult<Vec<SyncEvent>> {
+        self.sync_blog_ext(client, blog, max_pages, false)
+    }
+
+    ///Как [`sync_blog`](#method.sync_blog), но если `recheck` - true, то
+    ///страница каждого просмотренного поста загружается заново, даже если по
+    ///списку он не изменился. Так находятся правки под катом и правки и
+    ///удаления комментов, не видные по списку постов
+    pub fn sync_blog_ext(&mut self, client: &mut TClient, blog: &str, max_pages: u32, recheck: bool) -> TabunResult<Vec<SyncEvent>> {
+        try!(self.connection().execute_batch(SYNC_SCHEMA));
+        self.transaction(|archive| archive.sync_pages(client, blog, max_pages, recheck))
+    }
+
+    fn sync_pages(&mut self, client: &mut TClient, blog: &str, max_pages: u32, recheck: bool) -> TabunResult<Vec<SyncEvent>> {
+        let mut events = Vec::new();
+        let last_post_id = try!(self.last_post_id(blog));
+        let mut seen = Vec::new();
+        let mut oldest_date = None;
+
+        for page in 1..max_pages + 1 {
+            let list = try!(client.get_posts(blog, page));
+            let mut has_new = false;
+
+            if let Some(x) = list.items.last() {
+                oldest_date = Some(x.date.clone());
+            }
+
+            for item in &list.items {
+                seen.push(item.id);
+                has_new = has_new || item.id > last_post_id;
+
+                let list_hash = hash(&item.body);
+                let old = try!(self.post_state(item.id));
+
+                let changed = match old {
+                    None        => true,
+                    Some(ref x) => recheck || x.list_hash != list_hash || x.comments_count != item.comments_count
+                };
+                if !changed {
+                    continue;
+                }
+
+                // Пост и комменты берутся с одной страницы
+                let post_url = format!("/blog/{}/{}.html", blog, item.id);
+                let doc = try!(client.get_document(&post_url));
+                let post = try!(client.doc_get_post(&doc));
+                let mut comments = try!(client.doc_get_comments(&doc, post_url.as_str()))
+                    .into_iter()
+                    .map(|(_, x)| x)
+                    .collect::<Vec<_>>();
+                comments.sort_by_key(|x| x.id);
+
+                let state = PostState{
+                    list_hash:          list_hash,
+                    body_hash:          hash(&post.body),
+                    comments_count:     item.comments_count,
+                };
+
+                match old {
+                    None                                            => events.push(SyncEvent::NewPost(post.clone())),
+                    Some(ref x) if x.body_hash != state.body_hash   => events.push(SyncEvent::EditedPost(post.clone())),
+                    Some(_)                                         => {}
+                }
+
+                // Пост, комменты и состояние поста пишутся вместе
+                try!(self.transaction(|archive| {
+                    try!(archive.save_post(blog, &post, &[]));
+                    try!(archive.sync_comments(item.id, &comments, &mut events));
+                    archive.set_post_state(blog, item.id, &state)
+                }));
+            }
+
+            if !has_new || list.next.is_none() {
+                break;
+            }
+        }
+
+        if let (Some(oldest_date), Some(&high)) = (oldest_date, seen.iter().max()) {
+            for id in try!(self.gone_posts(blog, &seen, &oldest_date)) {
+                try!(self.transaction(|archive| {
+                    try!(archive.set_post_deleted(id, true));
+                    try!(archive.connection().execute("DELETE FROM sync_posts WHERE post_id = ?", &[&(id as i64)]));
+                    Ok(())
+                }));
+                events.push(SyncEvent::DeletedPost(id));
+            }
+
+            try!(self.connection().execute("INSERT OR REPLACE INTO sync_blogs (blog, last_post_id) VALUES (?, ?)",
+                &[&blog, &(max(high, last_post_id) as i64)]));
+        }
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use archive::Archive;
+    use ::Comment;
+    use ::test::post;
+
+    fn comment(id: u32, body: &str, deleted: bool) -> Comment {
+        Comment{
+            body:       body.to_string(),
+            id:         id,
+            author:     "Orhideous".to_string(),
+            date:       "2016-09-01T12:00:00+03:00".to_string(),
+            votes:      0,
+            parent:     0,
+            post_id:    1,
+            deleted:    deleted,
+        }
+    }
+
+    #[test]
+    fn test_sync_comments() {
+        let mut archive = Archive::open(":memory:").unwrap();
+        archive.connection().execute_batch(SYNC_SCHEMA).unwrap();
+
+        let mut events = Vec::new();
+        archive.sync_comments(1, &[comment(10, "раз", false), comment(11, "два", false), comment(12, "три", false)], &mut events).unwrap();
+        assert_eq!(events.len(), 3);
+
+        let mut events = Vec::new();
+        archive.sync_comments(1, &[comment(10, "раз", false), comment(11, "два!", false), comment(13, "четыре", false)], &mut events).unwrap();
+
+        let kinds = events.iter().map(|x| match *x {
+            SyncEvent::NewComment(ref c)                => format!("new {}", c.id),
+            SyncEvent::EditedComment(ref c)             => format!("edited {}", c.id),
+            SyncEvent::DeletedComment{id, ..}           => format!("deleted {}", id),
+            _                                           => unreachable!()
+        }).collect::<Vec<_>>();
+        assert_eq!(kinds, vec!["edited 11", "new 13", "deleted 12"]);
+
+        let mut events = Vec::new();
+        archive.sync_comments(1, &[comment(10, "", true)], &mut events).unwrap();
+        match events[0] {
+            SyncEvent::DeletedComment{id, ..} => assert_eq!(id, 10),
+            _                                 => unreachable!()
+        }
+    }
+
+    #[test]
+    fn test_gone_posts() {
+        let mut archive = Archive::open(":memory:").unwrap();
+        archive.connection().execute_batch(SYNC_SCHEMA).unwrap();
+
+        // Пост 2 - черновик, опубликованный позже всех
+        for &(id, date) in &[(1, "2016-09-01"), (2, "2016-09-09"), (3, "2016-09-03"), (4, "2016-09-04"), (5, "2016-09-05")] {
+            let mut post = post(id);
+            post.date = format!("{}T12:00:00+03:00", date);
+            archive.save_post("news", &post, &[]).unwrap();
+            archive.set_post_state("news", id, &PostState{
+                list_hash:      String::new(),
+                body_hash:      String::new(),
+                comments_count: 0,
+            }).unwrap();
+        }
+
+        // На странице 2, 5, 4: посты 3 и 1 старше и просто не просмотрены
+        assert!(archive.gone_posts("news", &[2, 5, 4], "2016-09-04T12:00:00+03:00").unwrap().is_empty());
+        assert_eq!(archive.gone_posts("news", &[5, 4], "2016-09-04T12:00:00+03:00").unwrap(), vec![2]);
+    }
+}
@@ -25,9 +25,7 @@ use super::*;
 use select::document::Document;
 use select::predicate::{Class, Name, And};
 
-use std::{str,convert};
-
-use regex::Regex;
+use std::convert;
 
 use hyper::header::Referer;
 
@@ -114,10 +112,11 @@ impl<'a> TClient<'a> {
 
         let res = try!(self.post_multipart("/talk/add", &fields));
 
-        if let Some(x) = res.headers.get_raw("location") {
-            parse_text_to_res!(regex => r"read/(\d+)/$", st => str::from_utf8(&x[0]).unwrap(), num => 1, typ => u32)
-        } else {
-            Err(TalkError::NoMembers)
+        match self.parse_form_redirect(res, r"read/(\d+)/$") {
+            Ok(x) => Ok(x),
+            // Без объяснений Табун не принимает диалог только без участников
+            Err(TabunError::ValidationError(ref errors)) if errors.is_empty() => Err(TalkError::NoMembers),
+            Err(x) => Err(TalkError::from(x))
         }
     }
 
@@ -128,9 +127,23 @@ impl<'a> TClient<'a> {
     ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
     ///user.get_talks(1);
     ///```
-    pub fn get_talks(&mut self, page: u32) -> TabunResult<Vec<TalkItem>> {
+    pub fn get_talks(&mut self, page: u32) -> TabunResult<Page<TalkItem>> {
         let doc = try!(self.get_document(&format!("/talk/inbox/page{}/", page)));
-        self.doc_get_talks(&doc)
+        let talks = try!(self.doc_get_talks(&doc));
+        Ok(self.doc_get_page(&doc, talks))
+    }
+
+    ///Все личные сообщения, страницы загружаются по мере перебора
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///for talk in user.talks().start(2).pages(5) {
+    ///    println!("{}", talk.unwrap().title);
+    ///}
+    ///```
+    pub fn talks<'c>(&'c mut self) -> Listing<'c, 'a, TalkItem> {
+        Listing::new(self, Box::new(|page| format!("/talk/inbox/page{}/", page)), TClient::doc_get_talks)
     }
 
     pub fn doc_get_talks(&mut self, doc: &Document) -> TabunResult<Vec<TalkItem>> {
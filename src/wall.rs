@@ -0,0 +1,234 @@
+/* Profile wall
+ *
+ * Copyright (C) 2016 TyanNN <TyanNN@cocaine.ninja>
+ *
+ * This program is free software; you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation; either version 2 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along
+ * with this program; if not, write to the Free Software Foundation, Inc.,
+ * 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+*/
+
+extern crate select;
+
+use super::*;
+
+use select::document::Document;
+use select::node::Node;
+use select::predicate::{Class, Name};
+
+///Парсит одну запись или ответ со стены, id берётся из атрибута `id`
+///с указанным префиксом
+fn parse_wall_item(item: &Node, prefix: &str) -> TabunResult<WallEntry> {
+    let id = try_to_parse!(hado!{
+        attr <- item.attr("id");
+        id_s <- attr.split(prefix).last();
+        id_s.parse::<u32>().ok()
+    });
+
+    let comm = if item.is(Class("comment")) {
+        item.clone()
+    } else {
+        try_to_parse!(item.find(Class("comment")).first())
+    };
+
+    let author = try_to_parse!(comm.find(Class("comment-author")).find(Name("a")).first()).text();
+    let author = author.trim().to_string();
+
+    let date = try_to_parse!(comm.find(Name("time")).first());
+    let date = try_to_parse!(date.attr("datetime")).to_string();
+
+    let body = try_to_parse!(comm.find(Class("comment-content")).first()).inner_html();
+    let body = body.trim().to_string();
+
+    Ok(WallEntry{
+        id:             id,
+        author:         author,
+        date:           date,
+        body:           body,
+        replies:        Vec::new(),
+        replies_count:  0,
+    })
+}
+
+impl<'a> TClient<'a> {
+
+    ///Получает записи со стены пользователя. Если `before` указан как None,
+    ///то получает первую страницу стены, иначе — записи старше записи
+    ///с указанным ID (так стена листается на самом сайте).
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///let wall = user.get_wall("Orhideous", None).unwrap();
+    ///if let Some(last) = wall.last() {
+    ///    let older = user.get_wall("Orhideous", last.id).unwrap();
+    ///}
+    ///```
+    pub fn get_wall<T: Into<Option<u32>>>(&mut self, login: &str, before: T) -> TabunResult<Vec<WallEntry>> {
+        match before.into() {
+            None => {
+                let doc = try!(self.get_document(&format!("/profile/{}/wall/", login)));
+                self.doc_get_wall(&doc)
+            },
+            Some(id) => {
+                let id = id.to_string();
+
+                let data = try!(self.ajax(
+                    &format!("/profile/{}/wall/load/", login),
+                    &vec![
+                        ("iIdLess", id.as_str()),
+                        ("iIdMore", "")
+                    ]
+                ));
+
+                let text = match get_json!(data, "/sText", as_str) {
+                    Some(x) => x,
+                    None => return Err(parse_error!("Server did not return sText"))
+                };
+
+                self.doc_get_wall(&Document::from(text))
+            }
+        }
+    }
+
+    ///Парсит записи стены вместе с показанными на странице ответами
+    pub fn doc_get_wall(&self, doc: &Document) -> TabunResult<Vec<WallEntry>> {
+        let mut ret = Vec::new();
+
+        let items = doc.find(Class("comment-wrapper")).iter()
+            .filter(|x| x.attr("id").map_or(false, |id| id.starts_with("wall-item-")))
+            .collect::<Vec<_>>();
+
+        for item in items {
+            let mut entry = try!(parse_wall_item(&item, "wall-item-"));
+
+            entry.replies = try!(self.doc_get_wall_replies(&item));
+
+            entry.replies_count = match item.find(Class("wall-more-reply")).find(Name("span")).iter()
+                .find(|x| x.attr("id").map_or(false, |id| id.starts_with("wall-reply-count-next-"))) {
+                    Some(x) => try_to_parse!(x.text().trim().parse::<u32>().ok()),
+                    None    => entry.replies.len() as u32
+                };
+
+            ret.push(entry);
+        }
+
+        Ok(ret)
+    }
+
+    ///Получает ответы на запись стены. Если `before` указан как None, то получает
+    ///последние ответы, иначе — ответы старше ответа с указанным ID.
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///user.get_wall_replies("Orhideous", 1234, None);
+    ///```
+    pub fn get_wall_replies<T: Into<Option<u32>>>(&mut self, login: &str, entry_id: u32, before: T) -> TabunResult<Vec<WallEntry>> {
+        let entry_id = entry_id.to_string();
+        let before = match before.into() {
+            Some(x) => x.to_string(),
+            None    => String::new()
+        };
+
+        let data = try!(self.ajax(
+            &format!("/profile/{}/wall/load-reply/", login),
+            &vec![
+                ("iPid", entry_id.as_str()),
+                ("iIdLess", before.as_str()),
+                ("iIdMore", "")
+            ]
+        ));
+
+        let text = match get_json!(data, "/sText", as_str) {
+            Some(x) => x,
+            None => return Err(parse_error!("Server did not return sText"))
+        };
+
+        let doc = Document::from(text);
+        let root = try_to_parse!(doc.find(Name("body")).first());
+        self.doc_get_wall_replies(&root)
+    }
+
+    ///Парсит ответы на запись стены, находящиеся внутри указанного элемента
+    pub fn doc_get_wall_replies(&self, node: &Node) -> TabunResult<Vec<WallEntry>> {
+        let mut ret = Vec::new();
+
+        let replies = node.find(Class("comment")).iter()
+            .filter(|x| x.attr("id").map_or(false, |id| id.starts_with("wall-reply-item-")))
+            .collect::<Vec<_>>();
+
+        for reply in replies {
+            ret.push(try!(parse_wall_item(&reply, "wall-reply-item-")));
+        }
+
+        Ok(ret)
+    }
+
+    ///Пишет на стену пользователя, reply=0 - новая запись,
+    ///иначе ответ на запись с указанным ID
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///user.wall_add("Orhideous", "Привет!", 0);
+    ///```
+    pub fn wall_add(&mut self, login: &str, body: &str, reply: u32) -> TabunResult<()> {
+        let reply = if reply == 0 { String::new() } else { reply.to_string() };
+
+        try!(self.ajax(
+            &format!("/profile/{}/wall/add/", login),
+            &vec![
+                ("sText", body),
+                ("iPid", reply.as_str())
+            ]
+        ));
+        Ok(())
+    }
+
+    ///Удаляет запись или ответ со стены пользователя
+    ///
+    ///# Examples
+    ///```no_run
+    ///# let mut user = libtabun::TClient::new("логин","пароль").unwrap();
+    ///user.wall_remove("Orhideous", 1234);
+    ///```
+    pub fn wall_remove(&mut self, login: &str, id: u32) -> TabunResult<()> {
+        let id = id.to_string();
+
+        try!(self.ajax(
+            &format!("/profile/{}/wall/remove/", login),
+            &vec![("iId", id.as_str())]
+        ));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ::TClient;
+
+    #[test]
+    fn test_get_wall() {
+        let mut user = TClient::new(None,None).unwrap();
+        match user.get_wall("Orhideous", None) {
+            Ok(x)   => {
+                for entry in &x {
+                    assert!(entry.id > 0);
+                    assert!(!entry.author.is_empty());
+                    assert!(entry.replies_count as usize >= entry.replies.len());
+                }
+            },
+            Err(x)  => panic!(x)
+        }
+    }
+}